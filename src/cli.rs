@@ -1,7 +1,7 @@
 //! The CLI for `st`.
 
 use crate::{
-    config::{prompt_for_configuration, StConfig},
+    config::{prompt_for_configuration, set_value, ConfigScope, StConfig},
     ctx::StContext,
     errors::{StError, StResult},
     subcommands::Subcommands,
@@ -23,6 +23,9 @@ pub struct Cli {
     /// Verbosity level (0-4)
     #[arg(short, action = ArgAction::Count)]
     pub v: u8,
+    /// Name of the remote to use, overriding the repository's configured remote
+    #[arg(long, global = true)]
+    pub remote: Option<String>,
     /// The subcommand to run
     #[clap(subcommand)]
     pub subcommand: Subcommands,
@@ -33,22 +36,39 @@ impl Cli {
     pub async fn run(self) -> StResult<()> {
         // Load the active repository.
         let repo = crate::git::active_repository().ok_or(StError::NotAGitRepository)?;
-        let config = Self::load_cfg_or_initialize()?;
+        // The configuration may be inspected and changed before it is complete.
+        let config = match self.subcommand {
+            Subcommands::Config(_) => StConfig::try_load(&repo, self.remote.as_deref())?,
+            _ => Self::load_cfg_or_initialize(&repo, self.remote.as_deref())?,
+        };
         let context = Self::load_ctx_or_initialize(config, &repo)?;
+
         self.subcommand.run(context).await
     }
 
-    /// Loads the [StConfig]. If the config does not exist or is the default config, prompts
-    /// the user to set up the `st` for the first time.
+    /// Loads the [StConfig] for the given [Repository]. If the config is incomplete, prompts the
+    /// user to set up the global configuration of `st` for the first time.
+    ///
+    /// ## Takes
+    /// - `repo` - The repository to load the config for.
+    /// - `remote` - The name of the remote to use, overriding the configured remote.
     ///
     /// ## Returns
-    /// - `Result<StConfig>` - The global `st` config.
-    pub(crate) fn load_cfg_or_initialize() -> StResult<StConfig> {
-        // Load the global configuration for `st`, or initialize it if it doesn't exist.
-        match StConfig::try_load()? {
-            Some(config) if config.validate().is_ok() => Ok(config),
-            _ => prompt_for_configuration(None),
+    /// - `Result<StConfig>` - The resolved `st` config.
+    pub(crate) fn load_cfg_or_initialize(
+        repo: &Repository,
+        remote: Option<&str>,
+    ) -> StResult<StConfig> {
+        let config = StConfig::try_load(repo, remote)?;
+        if config.validate().is_ok() {
+            return Ok(config);
         }
+
+        // Initialize the global configuration, and resolve it again with the other scopes.
+        prompt_for_configuration(repo, ConfigScope::Global)?;
+        let config = StConfig::try_load(repo, remote)?;
+        config.validate()?;
+        Ok(config)
     }
 
     /// Loads the [StContext] for the given [Repository]. If the context does not exist,
@@ -60,9 +80,9 @@ impl Cli {
     /// ## Returns
     /// - `Result<StContext>` - The context for the repository.
     pub(crate) fn load_ctx_or_initialize(
-        config: StConfig,
+        mut config: StConfig,
         repo: &Repository,
-    ) -> StResult<StContext> {
+    ) -> StResult<StContext<'_>> {
         // Attempt to load the repository store, or create a new one if it doesn't exist.
         if let Some(ctx) = StContext::try_load(config.clone(), repo)? {
             return Ok(ctx);
@@ -86,6 +106,23 @@ impl Cli {
             .collect::<StResult<Vec<_>>>()?;
         let trunk_branch = Select::new(&setup_message, branches).prompt()?;
 
+        // If the repository has multiple remotes, ask the user which one hosts it on GitHub.
+        let remotes = repo
+            .remotes()?
+            .iter()
+            .flatten()
+            .map(ToOwned::to_owned)
+            .collect::<Vec<_>>();
+        if remotes.len() > 1 {
+            let remote = Select::new(
+                "Select the remote that hosts the repository on GitHub.",
+                remotes,
+            )
+            .prompt()?;
+            set_value(repo, ConfigScope::Repo, "remote", remote.as_str().into())?;
+            config.remote = Some(remote);
+        }
+
         // Print the welcome message.
         println!(
             "\nSuccessfully set up repository with `{}`. Happy stacking ✨📚\n",
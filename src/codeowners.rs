@@ -0,0 +1,241 @@
+//! Parsing and matching of `CODEOWNERS` files.
+//!
+//! Follows the rules that GitHub applies:
+//! - The file is read from `.github/`, the root of the repository, or `docs/`, in that order.
+//! - Each line holds a gitignore-style pattern followed by owners. The last matching line wins.
+//! - A line without owners removes the owners of earlier matches.
+
+use git2::Repository;
+use std::path::Path;
+
+/// The locations that GitHub reads a `CODEOWNERS` file from, in order of precedence.
+const CODEOWNERS_PATHS: &[&str] = &[".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"];
+
+/// The rules of a `CODEOWNERS` file.
+#[derive(Default, Debug, Clone, Eq, PartialEq)]
+pub struct CodeOwners {
+    /// The rules, in the order they appear in the file.
+    rules: Vec<Rule>,
+}
+
+/// A single line of a `CODEOWNERS` file.
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct Rule {
+    /// The gitignore-style pattern, without a leading `/`.
+    pattern: String,
+    /// Whether the pattern only matches relative to the root of the repository.
+    anchored: bool,
+    /// Whether the pattern only matches directories, i.e. it ends with a `/`.
+    directory: bool,
+    /// The owners, as user logins or `org/team` slugs, without the leading `@`.
+    owners: Vec<String>,
+}
+
+impl CodeOwners {
+    /// Loads the `CODEOWNERS` file from the tree of a commit, as GitHub reads it from the base
+    /// branch of a pull request.
+    ///
+    /// ## Takes
+    /// - `repository` - The repository to read the file from.
+    /// - `revision` - The revision whose tree holds the file, e.g. `refs/remotes/origin/main`.
+    ///
+    /// ## Returns
+    /// - `Some(CodeOwners)` - The rules of the first `CODEOWNERS` file found.
+    /// - `None` - If the revision does not exist, or has no `CODEOWNERS` file.
+    pub fn load(repository: &Repository, revision: &str) -> Option<Self> {
+        let tree = repository
+            .revparse_single(revision)
+            .and_then(|object| object.peel_to_tree())
+            .ok()?;
+        CODEOWNERS_PATHS
+            .iter()
+            .find_map(|path| {
+                let blob = tree
+                    .get_path(Path::new(path))
+                    .and_then(|entry| entry.to_object(repository))
+                    .and_then(|object| object.peel_to_blob())
+                    .ok()?;
+                String::from_utf8(blob.content().to_vec()).ok()
+            })
+            .map(|contents| Self::parse(&contents))
+    }
+
+    /// Parses the contents of a `CODEOWNERS` file. Owners given as email addresses are skipped,
+    /// because reviews can only be requested from users and teams.
+    pub fn parse(contents: &str) -> Self {
+        let rules = contents
+            .lines()
+            .filter_map(|line| {
+                let line = line.split_once('#').map_or(line, |(line, _)| line).trim();
+                let mut parts = line.split_whitespace();
+                let pattern = parts.next()?;
+                let owners = parts
+                    .filter_map(|owner| owner.strip_prefix('@'))
+                    .map(ToString::to_string)
+                    .collect();
+
+                let directory = pattern.ends_with('/');
+                let pattern = pattern.trim_end_matches('/');
+                let anchored = pattern.starts_with('/') || pattern.contains('/');
+                Some(Rule {
+                    pattern: pattern.trim_start_matches('/').to_string(),
+                    anchored,
+                    directory,
+                    owners,
+                })
+            })
+            .collect();
+        Self { rules }
+    }
+
+    /// Returns the owners of the given file.
+    ///
+    /// ## Takes
+    /// - `path` - The path of the file, relative to the root of the repository, using `/`.
+    ///
+    /// ## Returns
+    /// - `&[String]` - The owners of the last matching rule. Empty if no rule matches.
+    pub fn owners_of(&self, path: &str) -> &[String] {
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| rule.matches(path))
+            .map(|rule| rule.owners.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Returns the owners of any of the given files, without duplicates, in order of appearance.
+    pub fn owners_of_all<'a>(&self, paths: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+        let mut owners = Vec::<String>::new();
+        for path in paths {
+            for owner in self.owners_of(path) {
+                if !owners.iter().any(|o| o.eq_ignore_ascii_case(owner)) {
+                    owners.push(owner.clone());
+                }
+            }
+        }
+        owners
+    }
+}
+
+impl Rule {
+    /// Returns whether the rule matches the file at `path`, or any directory containing it.
+    fn matches(&self, path: &str) -> bool {
+        let segments = path.split('/').collect::<Vec<_>>();
+
+        // The pattern may match the file itself, or any of its parent directories. Directory
+        // patterns only match the latter.
+        let ends = if self.directory {
+            1..segments.len()
+        } else {
+            1..segments.len() + 1
+        };
+        ends.into_iter().any(|end| {
+            let candidate = &segments[..end];
+            if self.anchored || self.pattern == "*" {
+                glob_match(&self.pattern, &candidate.join("/"))
+            } else {
+                // Unanchored patterns match at any depth.
+                (0..candidate.len())
+                    .any(|start| glob_match(&self.pattern, &candidate[start..].join("/")))
+            }
+        })
+    }
+}
+
+/// Matches a path against a glob pattern, where `*` matches within a path segment, `**` matches
+/// across segments, and `?` matches a single character other than `/`.
+fn glob_match(pattern: &str, path: &str) -> bool {
+    fn inner(pattern: &[u8], path: &[u8]) -> bool {
+        match pattern {
+            [] => path.is_empty(),
+            [b'*', b'*', b'/', rest @ ..] => {
+                // `**/` matches zero or more leading directories.
+                inner(rest, path)
+                    || path
+                        .iter()
+                        .enumerate()
+                        .any(|(i, c)| *c == b'/' && inner(rest, &path[i + 1..]))
+            }
+            [b'*', b'*', rest @ ..] => (0..=path.len()).any(|i| inner(rest, &path[i..])),
+            [b'*', rest @ ..] => (0..=path.len())
+                .take_while(|i| *i == 0 || path[i - 1] != b'/')
+                .any(|i| inner(rest, &path[i..])),
+            [b'?', rest @ ..] => {
+                path.first().is_some_and(|c| *c != b'/') && inner(rest, &path[1..])
+            }
+            [c, rest @ ..] => path.first() == Some(c) && inner(rest, &path[1..]),
+        }
+    }
+    inner(pattern.as_bytes(), path.as_bytes())
+}
+
+#[cfg(test)]
+mod test {
+    use super::CodeOwners;
+    use git2::{Repository, Signature};
+    use std::path::Path;
+
+    #[test]
+    fn last_matching_rule_wins() {
+        let owners = CodeOwners::parse(
+            "# Default owners\n\
+             *       @org/core\n\
+             *.rs    @rustacean # Rust files\n\
+             /docs/  @org/docs docs@example.com\n\
+             apps/   @apps\n\
+             **/generated/** \n\
+             src/cli.rs @cli-owner\n",
+        );
+
+        #[rustfmt::skip]
+        let cases: [(&str, &[&str]); 8] = [
+            ("README.md", &["org/core"]),
+            ("src/main.rs", &["rustacean"]),
+            ("docs/guide/index.md", &["org/docs"]),
+            ("src/docs/index.md", &["org/core"]),
+            ("apps/web/index.ts", &["apps"]),
+            ("nested/apps/web/index.ts", &["apps"]),
+            ("src/generated/schema.rs", &[]),
+            ("src/cli.rs", &["cli-owner"]),
+        ];
+        for (path, expected) in cases {
+            assert_eq!(owners.owners_of(path), expected, "{}", path);
+        }
+
+        assert_eq!(
+            owners.owners_of_all(["README.md", "src/main.rs", "LICENSE"]),
+            vec!["org/core", "rustacean"]
+        );
+    }
+
+    #[test]
+    fn loads_from_the_tree_of_a_revision() {
+        let dir = tempfile::tempdir().unwrap();
+        let repository = Repository::init(dir.path()).unwrap();
+        std::fs::create_dir(dir.path().join(".github")).unwrap();
+        std::fs::write(dir.path().join(".github/CODEOWNERS"), "* @committed\n").unwrap();
+
+        let mut index = repository.index().unwrap();
+        index.add_path(Path::new(".github/CODEOWNERS")).unwrap();
+        let tree = repository.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::now("st", "st@example.com").unwrap();
+        repository
+            .commit(
+                Some("refs/heads/main"),
+                &signature,
+                &signature,
+                "base",
+                &tree,
+                &[],
+            )
+            .unwrap();
+
+        // Changes in the working tree are not what GitHub applies.
+        std::fs::write(dir.path().join(".github/CODEOWNERS"), "* @uncommitted\n").unwrap();
+
+        let owners = CodeOwners::load(&repository, "refs/heads/main").unwrap();
+        assert_eq!(owners.owners_of("README.md"), ["committed"]);
+        assert!(CodeOwners::load(&repository, "refs/heads/missing").is_none());
+    }
+}
@@ -0,0 +1,705 @@
+//! Resolution of the [StConfig] from its layered sources.
+//!
+//! Sources are applied in increasing order of precedence:
+//! 1. The global file, `$XDG_CONFIG_HOME/st/config.toml` or the legacy `~/.st.toml`.
+//! 2. The team file, `.st/config.toml` in the root of the repository, which may be committed.
+//! 3. The repository file, `.st_config.toml` in the common git directory.
+//! 4. `ST_*` environment variables, e.g. `ST_GITHUB_TOKEN`.
+
+use super::{StConfig, StConfigError};
+use crate::{
+    constants::{
+        ST_CFG_DIR_NAME, ST_CFG_FILE_NAME, ST_ENV_PREFIX, ST_LEGACY_CFG_FILE_NAME,
+        ST_REPO_CFG_FILE_NAME, ST_TEAM_CFG_PATH,
+    },
+    git::RepositoryExt,
+};
+use git2::Repository;
+use nu_ansi_term::Color;
+use std::{
+    fmt::{self, Display},
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+use toml::{Table, Value};
+
+/// Top-level configuration keys that hold a single value. Each of them can also be set through an
+/// environment variable, e.g. `ST_GITHUB_TOKEN` for `github_token`.
+const SCALAR_KEYS: &[&str] = &[
+    "github_token",
+    "metadata_backend",
+    "worktree_dir",
+    "remote",
+    "push_remote",
+];
+
+/// Keys within each `hosts.<host>` table.
+const HOST_KEYS: &[&str] = &["api_url", "web_url", "token"];
+
+/// Keys within the `submit` table.
+const SUBMIT_KEYS: &[&str] = &[
+    "reviewers",
+    "labels",
+    "assignees",
+    "milestone",
+    "codeowners",
+    "comment_template",
+    "stack_overview",
+    "draft_upstack",
+];
+
+/// Keys within the `merge` table.
+const MERGE_KEYS: &[&str] = &["method"];
+
+/// Top-level keys that the team file may not set. The team file is committed to the repository,
+/// so anyone with push access could otherwise capture the token, or redirect pushes.
+const UNTRUSTED_TEAM_KEYS: &[&str] = &["github_token", "remote", "push_remote"];
+
+/// A source of configuration values, in increasing order of precedence.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
+pub enum ConfigScope {
+    /// The global configuration file of the user.
+    Global,
+    /// The configuration file shared by a team, committed to the repository.
+    Team,
+    /// The configuration file of the repository, within the git directory.
+    Repo,
+    /// Environment variables.
+    Env,
+}
+
+impl Display for ConfigScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Global => write!(f, "global"),
+            Self::Team => write!(f, "team"),
+            Self::Repo => write!(f, "repo"),
+            Self::Env => write!(f, "env"),
+        }
+    }
+}
+
+/// A single source of configuration values.
+#[derive(Debug, Clone)]
+struct ConfigLayer {
+    /// A description of where the values came from, e.g. `global:/home/user/.st.toml`.
+    origin: String,
+    /// The file that the values were read from, if any.
+    path: Option<PathBuf>,
+    /// The values of the layer.
+    table: Table,
+}
+
+/// The configuration values of every source, ordered by increasing precedence.
+#[derive(Debug, Clone, Default)]
+pub struct LayeredConfig {
+    layers: Vec<ConfigLayer>,
+}
+
+impl LayeredConfig {
+    /// Loads the configuration layers that apply to the given [Repository].
+    pub fn load(repository: &Repository) -> Result<Self, StConfigError> {
+        let mut layers = Vec::new();
+
+        for scope in [ConfigScope::Global, ConfigScope::Team, ConfigScope::Repo] {
+            let Ok(path) = Self::path_of(repository, scope) else {
+                continue;
+            };
+            if let Some(mut table) = read_table(&path)? {
+                if scope == ConfigScope::Team {
+                    for key in strip_untrusted(&mut table) {
+                        eprintln!(
+                            "{} Ignoring `{}` in `{}`, as the team configuration may not set it. Set it in the global or repo configuration instead.",
+                            Color::Yellow.paint("warning:"),
+                            Color::Blue.paint(key),
+                            path.display()
+                        );
+                    }
+                }
+                layers.push(ConfigLayer {
+                    origin: format!("{}:{}", scope, path.display()),
+                    path: Some(path),
+                    table,
+                });
+            }
+        }
+
+        for key in SCALAR_KEYS {
+            let var = format!("{}{}", ST_ENV_PREFIX, key.to_ascii_uppercase());
+            if let Ok(value) = std::env::var(&var) {
+                let mut table = Table::new();
+                table.insert(key.to_string(), Value::String(value));
+                layers.push(ConfigLayer {
+                    origin: format!("{}:{}", ConfigScope::Env, var),
+                    path: None,
+                    table,
+                });
+            }
+        }
+
+        Ok(Self { layers })
+    }
+
+    /// Returns the path of the configuration file of the given [ConfigScope].
+    ///
+    /// ## Returns
+    /// - `Ok(PathBuf)` - The path of the file, which may not exist yet.
+    /// - `Err(_)` - If the scope is not backed by a file in this repository.
+    pub fn path_of(repository: &Repository, scope: ConfigScope) -> Result<PathBuf, StConfigError> {
+        match scope {
+            ConfigScope::Global => global_config_path(),
+            ConfigScope::Team => repository
+                .workdir()
+                .map(|dir| dir.join(ST_TEAM_CFG_PATH))
+                .ok_or(StConfigError::ScopeUnavailable(scope)),
+            ConfigScope::Repo => Ok(repository.common_dir().join(ST_REPO_CFG_FILE_NAME)),
+            ConfigScope::Env => Err(StConfigError::ScopeUnavailable(scope)),
+        }
+    }
+
+    /// Builds a [LayeredConfig] from `(origin, toml)` pairs, in increasing order of precedence.
+    /// Layers with the `team` origin are restricted like the team file.
+    #[cfg(test)]
+    pub(crate) fn from_tables(layers: &[(&str, &str)]) -> Self {
+        Self {
+            layers: layers
+                .iter()
+                .map(|(origin, contents)| {
+                    let mut table = contents.parse::<Table>().unwrap();
+                    if *origin == ConfigScope::Team.to_string() {
+                        strip_untrusted(&mut table);
+                    }
+                    ConfigLayer {
+                        origin: origin.to_string(),
+                        path: None,
+                        table,
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /// Warns about configuration files that hold a GitHub token, but are readable by other users.
+    pub fn warn_exposed_tokens(&self) {
+        self.layers
+            .iter()
+            .filter(|layer| has_token(&layer.table))
+            .filter_map(|layer| layer.path.as_deref())
+            .filter(|path| is_exposed(path))
+            .for_each(|path| {
+                eprintln!(
+                    "{} `{}` contains a GitHub token, but is readable by other users. Restrict it with `{}`.",
+                    Color::Yellow.paint("warning:"),
+                    path.display(),
+                    Color::Blue.paint(format!("chmod 600 {}", path.display()))
+                );
+            });
+    }
+
+    /// Merges all layers into the resolved [StConfig].
+    pub fn resolve(&self) -> Result<StConfig, StConfigError> {
+        self.merged()
+            .try_into()
+            .map_err(|e| StConfigError::FailedToLoad(io::Error::new(io::ErrorKind::InvalidData, e)))
+    }
+
+    /// Returns the resolved value of a configuration key, along with the origin of the layer
+    /// with the highest precedence that sets it.
+    ///
+    /// ## Takes
+    /// - `key` - The dotted configuration key, e.g. `hosts."github.example.com".api_url`.
+    ///
+    /// ## Returns
+    /// - `Some((Value, String))` - The value and its origin.
+    /// - `None` - If no layer sets the key.
+    pub fn get(&self, key: &str) -> Result<Option<(Value, String)>, StConfigError> {
+        let segments = parse_key(key)?;
+        let Some(value) = lookup(&self.merged(), &segments).cloned() else {
+            return Ok(None);
+        };
+        Ok(Some((value, self.origin_of(&segments))))
+    }
+
+    /// Returns every resolved value, keyed by its dotted configuration key and paired with the
+    /// origin of the layer with the highest precedence that sets it.
+    pub fn entries(&self) -> Vec<(String, Value, String)> {
+        let mut entries = Vec::new();
+        flatten(&self.merged(), &mut Vec::new(), &mut entries);
+        entries
+            .into_iter()
+            .map(|(segments, value)| {
+                let origin = self.origin_of(&segments);
+                (format_key(&segments), value, origin)
+            })
+            .collect()
+    }
+
+    /// Deep-merges all layers, with later layers taking precedence.
+    fn merged(&self) -> Table {
+        let mut merged = Table::new();
+        self.layers
+            .iter()
+            .for_each(|layer| merge_into(&mut merged, &layer.table));
+        merged
+    }
+
+    /// Returns the origin of the layer with the highest precedence that sets the key.
+    fn origin_of(&self, segments: &[String]) -> String {
+        self.layers
+            .iter()
+            .rev()
+            .find(|layer| lookup(&layer.table, segments).is_some())
+            .map(|layer| layer.origin.clone())
+            .unwrap_or_default()
+    }
+}
+
+/// Sets a configuration key in the configuration file of the given [ConfigScope], preserving the
+/// formatting and comments of the rest of the file.
+///
+/// ## Takes
+/// - `repository` - The repository whose configuration is changed.
+/// - `scope` - The scope of the configuration file. The file is created if it does not exist.
+/// - `key` - The dotted configuration key.
+/// - `value` - The new value.
+///
+/// ## Returns
+/// - `Result<PathBuf>` - The path of the changed configuration file.
+pub fn set_value(
+    repository: &Repository,
+    scope: ConfigScope,
+    key: &str,
+    value: toml_edit::Value,
+) -> Result<PathBuf, StConfigError> {
+    let segments = parse_key(key)?;
+    validate_key(key, &segments)?;
+    if scope == ConfigScope::Team && !is_trusted_in_team(&segments) {
+        return Err(StConfigError::UntrustedTeamKey(key.to_string()));
+    }
+
+    let path = LayeredConfig::path_of(repository, scope)?;
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(StConfigError::FailedToLoad(e)),
+    };
+    let mut document = contents
+        .parse::<toml_edit::DocumentMut>()
+        .map_err(|e| StConfigError::FailedToLoad(io::Error::new(io::ErrorKind::InvalidData, e)))?;
+
+    // Walk down to the table holding the key, creating intermediate tables as needed.
+    let (last, parents) = segments
+        .split_last()
+        .expect("keys have at least one segment");
+    let mut table = document.as_table_mut();
+    for segment in parents {
+        let entry = table.entry(segment).or_insert_with(|| {
+            let mut table = toml_edit::Table::new();
+            table.set_implicit(true);
+            toml_edit::Item::Table(table)
+        });
+        table = entry
+            .as_table_mut()
+            .ok_or_else(|| StConfigError::InvalidKey(key.to_string()))?;
+    }
+    table[last.as_str()] = toml_edit::value(value);
+
+    // Ensure that the file still holds a valid configuration before writing it.
+    let serialized = document.to_string();
+    toml::from_str::<StConfig>(&serialized)
+        .map_err(|e| StConfigError::InvalidValue(key.to_string(), e.message().to_string()))?;
+
+    write_config_file(scope, &path, &serialized).map_err(StConfigError::FailedToLoad)?;
+    Ok(path)
+}
+
+/// Writes a configuration file, creating its parent directories as needed.
+///
+/// Files that are private to the user are made readable by the user only, as they may hold
+/// tokens. Existing files are restricted before they are written to. The team file is meant to
+/// be committed, and keeps default permissions.
+pub(super) fn write_config_file(scope: ConfigScope, path: &Path, contents: &str) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    if scope != ConfigScope::Team {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        match fs::set_permissions(path, fs::Permissions::from_mode(0o600)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = scope;
+
+    options.open(path)?.write_all(contents.as_bytes())
+}
+
+/// Returns whether the table holds a GitHub token, for any host.
+fn has_token(table: &Table) -> bool {
+    table.contains_key("github_token")
+        || table
+            .get("hosts")
+            .and_then(Value::as_table)
+            .is_some_and(|hosts| {
+                hosts
+                    .values()
+                    .filter_map(Value::as_table)
+                    .any(|host| host.contains_key("token"))
+            })
+}
+
+/// Returns whether the file at `path` is readable by users other than its owner.
+fn is_exposed(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::metadata(path).is_ok_and(|m| m.permissions().mode() & 0o077 != 0)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        false
+    }
+}
+
+/// Resolves the path of the global configuration file at runtime.
+///
+/// The file lives in `$XDG_CONFIG_HOME/st`, or `~/.config/st` if that is unset. The legacy
+/// `~/.st.toml` is used instead if it is the only one of the two files that exists.
+fn global_config_path() -> Result<PathBuf, StConfigError> {
+    let home = std::env::var_os("HOME")
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from);
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| home.as_ref().map(|home| home.join(".config")))
+        .ok_or(StConfigError::ConfigDirNotFound)?;
+
+    let path = config_dir.join(ST_CFG_DIR_NAME).join(ST_CFG_FILE_NAME);
+    match home.map(|home| home.join(ST_LEGACY_CFG_FILE_NAME)) {
+        Some(legacy) if !path.exists() && legacy.exists() => Ok(legacy),
+        _ => Ok(path),
+    }
+}
+
+/// Parses a value given on the command line as a TOML literal, falling back to a plain string for
+/// values that are not valid TOML.
+pub fn parse_value(raw_value: &str) -> toml_edit::Value {
+    format!("value = {}", raw_value)
+        .parse::<toml_edit::DocumentMut>()
+        .ok()
+        .and_then(|d| d.get("value").and_then(|v| v.as_value()).cloned())
+        .unwrap_or_else(|| raw_value.into())
+}
+
+/// Reads the TOML table at `path`, if the file exists.
+fn read_table(path: &Path) -> Result<Option<Table>, StConfigError> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(StConfigError::FailedToLoad(e)),
+    };
+
+    let invalid = |e: toml::de::Error| {
+        StConfigError::FailedToLoad(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", path.display(), e),
+        ))
+    };
+    let table = toml::from_str::<Table>(&contents).map_err(invalid)?;
+    toml::from_str::<StConfig>(&contents).map_err(invalid)?;
+    Ok(Some(table))
+}
+
+/// Splits a dotted configuration key into its segments, following TOML's dotted key syntax.
+fn parse_key(key: &str) -> Result<Vec<String>, StConfigError> {
+    let invalid = || StConfigError::InvalidKey(key.to_string());
+    let mut table = format!("{} = 0", key)
+        .parse::<Table>()
+        .map_err(|_| invalid())?;
+
+    let mut segments = Vec::new();
+    loop {
+        let (segment, value) = table.into_iter().next().ok_or_else(invalid)?;
+        segments.push(segment);
+        match value {
+            Value::Table(inner) => table = inner,
+            _ => return Ok(segments),
+        }
+    }
+}
+
+/// Ensures that the key is one that `st` understands.
+fn validate_key(key: &str, segments: &[String]) -> Result<(), StConfigError> {
+    let known = match segments {
+        [key] => SCALAR_KEYS.contains(&key.as_str()),
+        [table, key] => match table.as_str() {
+            "submit" => SUBMIT_KEYS.contains(&key.as_str()),
+            "merge" => MERGE_KEYS.contains(&key.as_str()),
+            _ => false,
+        },
+        [hosts, _, key] => hosts == "hosts" && HOST_KEYS.contains(&key.as_str()),
+        _ => false,
+    };
+    if known {
+        Ok(())
+    } else {
+        Err(StConfigError::UnknownKey(key.to_string()))
+    }
+}
+
+/// Returns whether the team file may set the key. Tokens, API endpoints, remotes and the files
+/// that `st` reads are reserved to the files of the user, see [UNTRUSTED_TEAM_KEYS].
+fn is_trusted_in_team(segments: &[String]) -> bool {
+    match segments {
+        [key] => !UNTRUSTED_TEAM_KEYS.contains(&key.as_str()),
+        [table, key] if table == "submit" => key != "comment_template",
+        [table, ..] => table != "hosts",
+        [] => true,
+    }
+}
+
+/// Removes the keys that the team file may not set from its table.
+///
+/// ## Returns
+/// - `Vec<String>` - The dotted keys that were removed.
+fn strip_untrusted(table: &mut Table) -> Vec<String> {
+    let mut entries = Vec::new();
+    flatten(table, &mut Vec::new(), &mut entries);
+    entries
+        .into_iter()
+        .map(|(segments, _)| segments)
+        .filter(|segments| !is_trusted_in_team(segments))
+        .map(|segments| {
+            remove(table, &segments);
+            format_key(&segments)
+        })
+        .collect()
+}
+
+/// Removes the value at the given key segments, along with the tables it leaves empty.
+fn remove(table: &mut Table, segments: &[String]) {
+    match segments {
+        [key] => {
+            table.remove(key);
+        }
+        [first, rest @ ..] => {
+            if let Some(Value::Table(inner)) = table.get_mut(first) {
+                remove(inner, rest);
+                if inner.is_empty() {
+                    table.remove(first);
+                }
+            }
+        }
+        [] => {}
+    }
+}
+
+/// Formats key segments as a dotted key, quoting segments that are not bare keys.
+fn format_key(segments: &[String]) -> String {
+    segments
+        .iter()
+        .map(|segment| {
+            let is_bare = !segment.is_empty()
+                && segment
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+            if is_bare {
+                segment.clone()
+            } else {
+                format!("{:?}", segment)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Looks up the value at the given key segments.
+fn lookup<'a>(table: &'a Table, segments: &[String]) -> Option<&'a Value> {
+    let (first, rest) = segments.split_first()?;
+    let value = table.get(first)?;
+    if rest.is_empty() {
+        return Some(value);
+    }
+    lookup(value.as_table()?, rest)
+}
+
+/// Recursively merges `overlay` into `base`. Tables are merged, while other values are replaced.
+fn merge_into(base: &mut Table, overlay: &Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(key), value) {
+            (Some(Value::Table(base)), Value::Table(overlay)) => merge_into(base, overlay),
+            _ => {
+                base.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+/// Collects the leaf values of the table, along with their key segments.
+fn flatten(table: &Table, prefix: &mut Vec<String>, entries: &mut Vec<(Vec<String>, Value)>) {
+    for (key, value) in table {
+        prefix.push(key.clone());
+        match value {
+            Value::Table(inner) => flatten(inner, prefix, entries),
+            _ => entries.push((prefix.clone(), value.clone())),
+        }
+        prefix.pop();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{format_key, parse_key, set_value, write_config_file, ConfigScope, LayeredConfig};
+    use crate::{
+        config::{MetadataBackend, StConfigError},
+        github::GitHubHost,
+    };
+    use git2::Repository;
+
+    #[test]
+    fn later_layers_take_precedence() {
+        let config = LayeredConfig::from_tables(&[
+            (
+                "global",
+                "github_token = \"a\"\nmetadata_backend = \"file\"\nremote = \"origin\"",
+            ),
+            ("team", "metadata_backend = \"ref\""),
+            ("repo", "remote = \"upstream\""),
+            ("env", "github_token = \"b\""),
+        ]);
+
+        let resolved = config.resolve().unwrap();
+        assert_eq!(resolved.github_token, "b");
+        assert_eq!(resolved.metadata_backend, MetadataBackend::Ref);
+        assert_eq!(resolved.remote.as_deref(), Some("upstream"));
+
+        let (value, origin) = config.get("metadata_backend").unwrap().unwrap();
+        assert_eq!(value.as_str(), Some("ref"));
+        assert_eq!(origin, "team");
+        assert!(config.get("push_remote").unwrap().is_none());
+    }
+
+    #[test]
+    fn tables_are_merged_across_layers() {
+        let config = LayeredConfig::from_tables(&[
+            (
+                "global",
+                "[hosts.\"ghe.corp\"]\napi_url = \"https://api.ghe.corp\"",
+            ),
+            (
+                "repo",
+                "[hosts.\"ghe.corp\"]\nweb_url = \"https://ghe.corp\"",
+            ),
+        ]);
+
+        let entries = config
+            .entries()
+            .into_iter()
+            .map(|(key, _, origin)| (key, origin))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            entries,
+            vec![
+                (
+                    "hosts.\"ghe.corp\".api_url".to_string(),
+                    "global".to_string()
+                ),
+                ("hosts.\"ghe.corp\".web_url".to_string(), "repo".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_dotted_keys() {
+        assert_eq!(parse_key("remote").unwrap(), ["remote"]);
+        assert_eq!(
+            parse_key("hosts.\"github.example.com\".api_url").unwrap(),
+            ["hosts", "github.example.com", "api_url"]
+        );
+        assert!(parse_key("hosts.").is_err());
+        assert_eq!(
+            format_key(&parse_key("hosts.\"github.example.com\".api_url").unwrap()),
+            "hosts.\"github.example.com\".api_url"
+        );
+    }
+
+    #[test]
+    fn team_layer_cannot_redirect_api_host() {
+        let config = LayeredConfig::from_tables(&[
+            (
+                "global",
+                "github_token = \"secret\"\n[hosts.\"ghe.corp\"]\napi_url = \"https://ghe.corp/api/v3\"",
+            ),
+            (
+                "team",
+                "github_token = \"team\"\nremote = \"attacker\"\npush_remote = \"attacker\"\nmetadata_backend = \"ref\"\n[hosts.\"ghe.corp\"]\napi_url = \"https://attacker.example\"\n[hosts.\"github.com\"]\napi_url = \"https://attacker.example\"\ntoken = \"team\"\n[submit]\nlabels = [\"team\"]\ncomment_template = \"../../secret\"",
+            ),
+        ]);
+
+        let resolved = config.resolve().unwrap();
+        assert_eq!(resolved.github_token, "secret");
+        assert_eq!(resolved.remote, None);
+        assert_eq!(resolved.push_remote, None);
+        assert_eq!(resolved.metadata_backend, MetadataBackend::Ref);
+        assert_eq!(
+            GitHubHost::resolve("ghe.corp", &resolved).api_url,
+            "https://ghe.corp/api/v3"
+        );
+        assert_eq!(
+            GitHubHost::resolve("github.com", &resolved).api_url,
+            "https://api.github.com"
+        );
+        let from_team = config
+            .entries()
+            .into_iter()
+            .filter(|(_, _, origin)| origin == "team")
+            .map(|(key, _, _)| key)
+            .collect::<Vec<_>>();
+        assert_eq!(from_team, ["metadata_backend", "submit.labels"]);
+        assert_eq!(resolved.submit.comment_template, None);
+
+        // Setting the keys in the team file is refused, and leaves the file untouched.
+        let dir = tempfile::tempdir().unwrap();
+        let repository = Repository::init(dir.path()).unwrap();
+        for key in [
+            "github_token",
+            "push_remote",
+            "hosts.\"ghe.corp\".api_url",
+            "submit.comment_template",
+        ] {
+            let result = set_value(&repository, ConfigScope::Team, key, "x".into());
+            assert!(
+                matches!(result, Err(StConfigError::UntrustedTeamKey(ref k)) if k == key),
+                "{}",
+                key
+            );
+        }
+        assert!(!LayeredConfig::path_of(&repository, ConfigScope::Team)
+            .unwrap()
+            .exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn restricts_existing_files_before_writing() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        write_config_file(ConfigScope::Repo, &path, "github_token = \"secret\"").unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
@@ -0,0 +1,403 @@
+//! Contains the configuration for `st`.
+
+use crate::{
+    constants::DEFAULT_REMOTE_NAME, errors::StResult, github::GITHUB_HOST, remote_url::RemoteUrl,
+};
+use clap::ValueEnum;
+use git2::Repository;
+use nu_ansi_term::Color;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, io, path::PathBuf};
+use thiserror::Error;
+
+mod layers;
+pub use layers::{parse_value, set_value, ConfigScope, LayeredConfig};
+
+mod token;
+use token::resolve_token;
+pub use token::TokenSource;
+
+pub(crate) const DEFAULT_CONFIG_PRETTY: &str = r#"# GitHub personal access token. Used for pushing branches to GitHub remotes as well as querying
+# information about the active repository.
+#
+# Must have the following scopes:
+# - repo:status
+# - repo:public_repo
+#
+# If you're planning to use st with private repositories, you'll need to add the full `repo` scope.
+github_token = ""
+
+# Where stack metadata is stored for each repository.
+#
+# - "file" keeps the stack tree in `.git/.st_store.toml`.
+# - "ref" keeps the stack tree in a commit under `refs/st/metadata`, which can be shared with other
+#   clones via `st metadata push` and `st metadata pull`.
+metadata_backend = "file"
+
+# Directory in which `st worktree add` creates worktrees. Relative paths are resolved against the
+# root of the repository. Defaults to a `<repository>.worktrees` directory next to the repository.
+# worktree_dir = "../worktrees"
+
+# API and web URLs of GitHub Enterprise Server instances, keyed by the host name of the remote.
+# Defaults to `https://<host>/api/v3` and `https://<host>` for hosts other than github.com.
+# [hosts."github.example.com"]
+# api_url = "https://github.example.com/api/v3"
+# web_url = "https://github.example.com"
+# token = ""
+#
+# When no token is configured for a host, `st` asks the git credential helpers and then the `gh` CLI."#;
+
+pub(crate) const DEFAULT_REPO_CONFIG_PRETTY: &str = r#"# Settings in this file apply to the current repository only, and take precedence over the global
+# configuration. Any setting of the global configuration may be overridden here.
+
+# Name of the remote that hosts the repository on GitHub. Pull requests are opened against this
+# remote, and branches are pushed to and pulled from it. Defaults to "origin".
+# remote = "origin"
+
+# Name of the remote that branches are pushed to, when contributing from a fork. Pull requests are
+# still opened against `remote`. Defaults to `remote`.
+# push_remote = "fork"
+
+# Defaults for the pull requests opened by `st submit`. Reviewers, labels and assignees are added to
+# new pull requests, and to existing ones when they are submitted again, without removing any that
+# were added by hand. Teams are given as `org/team`, and the milestone by its title or number.
+# [submit]
+# reviewers = ["octocat", "org/team"]
+# labels = ["stacked"]
+# assignees = ["octocat"]
+# milestone = "v1.0"
+#
+# Request reviews from the owners of the changed files, as listed in the repository's CODEOWNERS
+# file. Defaults to true.
+# codeowners = true
+#
+# Markdown template of the stack overview comment posted on each pull request, relative to the root
+# of the repository. Defaults to `.st/stack_comment.md` when it exists. The placeholders `{{stack}}`,
+# `{{trunk}}`, `{{branch}}` and `{{pr}}` are replaced with the tree of pull requests, the trunk
+# branch, the branch of the pull request and its number. The template must be within the
+# repository, and cannot be set in the team configuration.
+# comment_template = ".github/stack_comment.md"
+#
+# Where to maintain the stack overview: "comment" posts it as a comment on each pull request, and
+# "body" keeps it in a section at the end of the description, delimited by HTML comments. Text
+# outside of the section is left untouched. Switching removes the overview from the other place.
+# stack_overview = "comment"
+#
+# Keep every pull request above the lowest open one of its stack in draft, and mark the lowest one
+# as ready for review. Applied by `st submit`, `st merge` and `st ready`, unless the latter is passed
+# `--no-keep-upstack-draft`. Defaults to false.
+# draft_upstack = true
+
+# Settings of `st merge`.
+# [merge]
+# How pull requests are merged: "merge", "squash" or "rebase". Defaults to "merge".
+# method = "squash""#;
+
+/// The configuration for `st`, resolved from all [ConfigScope]s.
+#[derive(Default, Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct StConfig {
+    /// GitHub personal access token for github.com. Once loaded, holds the token resolved for the
+    /// host of the configured remote.
+    #[serde(default)]
+    pub github_token: String,
+    /// Where the GitHub token was resolved from. Never persisted.
+    #[serde(skip)]
+    pub token_source: TokenSource,
+    /// The host that the GitHub token was resolved for. Never persisted.
+    #[serde(skip)]
+    pub token_host: String,
+    /// The backend used to persist stack metadata.
+    #[serde(default)]
+    pub metadata_backend: MetadataBackend,
+    /// The directory in which worktrees for tracked branches are created.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub worktree_dir: Option<PathBuf>,
+    /// Endpoints of GitHub instances, keyed by host name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub hosts: BTreeMap<String, HostConfig>,
+    /// The name of the remote that hosts the repository on GitHub.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote: Option<String>,
+    /// The name of the remote that branches are pushed to, if it differs from `remote`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub push_remote: Option<String>,
+    /// Defaults for the pull requests opened by `submit`.
+    #[serde(default, skip_serializing_if = "SubmitConfig::is_default")]
+    pub submit: SubmitConfig,
+    /// Settings of `merge`.
+    #[serde(default, skip_serializing_if = "MergeConfig::is_default")]
+    pub merge: MergeConfig,
+}
+
+/// Defaults for the pull requests opened by `submit`.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SubmitConfig {
+    /// Users, or `org/team` teams, to request reviews from.
+    #[serde(default)]
+    pub reviewers: Vec<String>,
+    /// Labels to add to pull requests.
+    #[serde(default)]
+    pub labels: Vec<String>,
+    /// Users to assign to pull requests.
+    #[serde(default)]
+    pub assignees: Vec<String>,
+    /// The milestone to add pull requests to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub milestone: Option<MilestoneRef>,
+    /// Whether to request reviews from the code owners of the changed files.
+    #[serde(default = "default_codeowners")]
+    pub codeowners: bool,
+    /// The Markdown template of the stack comments, relative to the root of the repository.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment_template: Option<PathBuf>,
+    /// Where the stack overview of each pull request is maintained.
+    #[serde(default)]
+    pub stack_overview: StackOverviewMode,
+    /// Whether to keep the pull requests above the lowest open one of each stack in draft.
+    #[serde(default)]
+    pub draft_upstack: bool,
+}
+
+impl Default for SubmitConfig {
+    fn default() -> Self {
+        Self {
+            reviewers: Vec::new(),
+            labels: Vec::new(),
+            assignees: Vec::new(),
+            milestone: None,
+            codeowners: default_codeowners(),
+            comment_template: None,
+            stack_overview: StackOverviewMode::default(),
+            draft_upstack: false,
+        }
+    }
+}
+
+impl SubmitConfig {
+    /// Returns whether the configuration holds only default values.
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Code owners are requested as reviewers unless disabled.
+fn default_codeowners() -> bool {
+    true
+}
+
+/// Where `submit` maintains the stack overview of each pull request.
+#[derive(Default, Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StackOverviewMode {
+    /// The overview is posted as a comment on the pull request.
+    #[default]
+    Comment,
+    /// The overview is kept in a delimited section of the pull request's description.
+    Body,
+}
+
+/// Settings of `merge`.
+#[derive(Default, Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct MergeConfig {
+    /// How pull requests are merged.
+    #[serde(default)]
+    pub method: MergeMethod,
+}
+
+impl MergeConfig {
+    /// Returns whether the configuration holds only default values.
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// How `merge` merges pull requests into their base branch.
+#[derive(Default, Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum MergeMethod {
+    /// All commits are added to the base branch with a merge commit.
+    #[default]
+    Merge,
+    /// All commits are combined into a single commit on the base branch.
+    Squash,
+    /// All commits are added to the base branch individually, without a merge commit.
+    Rebase,
+}
+
+/// A reference to a milestone of the repository.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MilestoneRef {
+    /// The number of the milestone.
+    Number(u64),
+    /// The title of the milestone.
+    Title(String),
+}
+
+/// Endpoints of a GitHub instance, overriding the ones detected from its host name.
+#[derive(Default, Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct HostConfig {
+    /// The base URL of the REST API.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_url: Option<String>,
+    /// The base URL of the web interface.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub web_url: Option<String>,
+    /// The GitHub token for the instance.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+/// The storage backend for a repository's [StackTree].
+///
+/// [StackTree]: crate::tree::StackTree
+#[derive(Default, Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MetadataBackend {
+    /// The stack tree is stored in a file within the `.git` directory.
+    #[default]
+    File,
+    /// The stack tree is stored in a commit under [ST_METADATA_REF].
+    ///
+    /// [ST_METADATA_REF]: crate::constants::ST_METADATA_REF
+    Ref,
+}
+
+impl StConfig {
+    /// Loads the configuration for the given [Repository] from all [ConfigScope]s, and resolves the
+    /// GitHub token for the host of the configured remote.
+    ///
+    /// Tokens provided by git credential helpers or the `gh` CLI are only held in memory, and never
+    /// written to a configuration file.
+    ///
+    /// ## Takes
+    /// - `repository` - The repository to load the configuration for.
+    /// - `remote` - The name of the remote to use, overriding the configured remote.
+    pub fn try_load(repository: &Repository, remote: Option<&str>) -> StResult<Self> {
+        let layers = LayeredConfig::load(repository)?;
+        layers.warn_exposed_tokens();
+        let mut config = layers.resolve()?;
+        if let Some(remote) = remote {
+            config.remote = Some(remote.to_string());
+        }
+
+        // Resolve the token for the host that pull requests are opened against.
+        let remote = config.remote.as_deref().unwrap_or(DEFAULT_REMOTE_NAME);
+        let host = RemoteUrl::from_remote(repository, remote)
+            .map(|url| url.host)
+            .unwrap_or_else(|_| GITHUB_HOST.to_string());
+        let dir = repository.workdir().unwrap_or(repository.path());
+        let (token, source) = resolve_token(&host, &layers, dir)?.unwrap_or_default();
+        config.github_token = token;
+        config.token_source = source;
+        config.token_host = host;
+
+        Ok(config)
+    }
+
+    /// Validates the configuration.
+    pub fn validate(&self) -> Result<(), StConfigError> {
+        if self.github_token.is_empty() {
+            return Err(StConfigError::MissingToken(self.token_host.clone()));
+        }
+        Ok(())
+    }
+}
+
+/// Error type for [StConfig] operations.
+#[derive(Error, Debug)]
+pub enum StConfigError {
+    /// Failed to load the configuration file.
+    #[error("Failed to load the configuration file: {}", .0)]
+    FailedToLoad(io::Error),
+    /// No GitHub token was found for the host.
+    #[error(
+        "No GitHub token found for `{}`. Set `github_token` for github.com or `hosts.\"<host>\".token`, configure a git credential helper, or log in with `{}`.",
+        Color::Blue.paint(.0),
+        Color::Blue.paint("gh auth login")
+    )]
+    MissingToken(String),
+    /// The configuration key could not be parsed.
+    #[error("Invalid configuration key: `{}`", Color::Blue.paint(.0))]
+    InvalidKey(String),
+    /// The configuration key is not known to `st`.
+    #[error("Unknown configuration key: `{}`", Color::Blue.paint(.0))]
+    UnknownKey(String),
+    /// The configuration key is not set in any scope.
+    #[error("Configuration key `{}` is not set.", Color::Blue.paint(.0))]
+    UnsetKey(String),
+    /// The value is not valid for the configuration key.
+    #[error("Invalid value for `{}`: {}", Color::Blue.paint(.0), .1)]
+    InvalidValue(String, String),
+    /// The user's configuration directory could not be determined.
+    #[error("Could not determine the configuration directory. Set `HOME` or `XDG_CONFIG_HOME`.")]
+    ConfigDirNotFound,
+    /// The scope has no configuration file in the current repository.
+    #[error("The `{}` configuration is not available in this repository.", .0)]
+    ScopeUnavailable(ConfigScope),
+    /// The configuration key may not be set in the team configuration.
+    #[error(
+        "`{}` may not be set in the team configuration, which is committed to the repository. Set it in the global or repo configuration instead.",
+        Color::Blue.paint(.0)
+    )]
+    UntrustedTeamKey(String),
+}
+
+/// Prompts the user to edit the configuration file of the given [ConfigScope].
+///
+/// ## Takes
+/// - `repository` - The repository whose configuration is edited.
+/// - `scope` - The scope of the configuration file to edit.
+///
+/// ## Returns
+/// - `Result<StConfig>` - The configuration contained in the edited file.
+pub fn prompt_for_configuration(repository: &Repository, scope: ConfigScope) -> StResult<StConfig> {
+    let path = LayeredConfig::path_of(repository, scope)?;
+    let existing_config = fs::read_to_string(&path).ok();
+
+    let setup_text = match scope {
+        ConfigScope::Global => format!(
+            "{} configuration found for `{}`. Set up the environment.",
+            existing_config.as_ref().map(|_| "Existing").unwrap_or("No"),
+            Color::Blue.paint("st")
+        ),
+        _ => format!(
+            "Configure `{}` for the current repository ({}).",
+            Color::Blue.paint("st"),
+            scope
+        ),
+    };
+
+    // Use the existing file or fall back to the documented default.
+    let default_text = existing_config.unwrap_or_else(|| match scope {
+        ConfigScope::Global => DEFAULT_CONFIG_PRETTY.to_string(),
+        _ => DEFAULT_REPO_CONFIG_PRETTY.to_string(),
+    });
+
+    let ser_cfg = inquire::Editor::new(&setup_text)
+        .with_file_extension(".toml")
+        .with_predefined_text(&default_text)
+        .prompt()?;
+
+    let config: StConfig = toml::from_str(&ser_cfg)?;
+    layers::write_config_file(scope, &path, &ser_cfg)?;
+
+    Ok(config)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{StConfig, DEFAULT_CONFIG_PRETTY, DEFAULT_REPO_CONFIG_PRETTY};
+
+    #[test]
+    fn pretty_default_config_is_valid() {
+        let de = toml::from_str::<StConfig>(DEFAULT_CONFIG_PRETTY);
+        assert!(de.is_ok());
+    }
+
+    #[test]
+    fn pretty_default_repo_config_is_valid() {
+        let de = toml::from_str::<StConfig>(DEFAULT_REPO_CONFIG_PRETTY);
+        assert_eq!(de.unwrap(), StConfig::default());
+    }
+}
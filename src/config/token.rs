@@ -0,0 +1,241 @@
+//! Resolution of the GitHub token for a host.
+//!
+//! Sources are tried in order, and the first token found is used:
+//! 1. The `ST_GITHUB_TOKEN` environment variable.
+//! 2. The configuration files: `hosts."<host>".token`, or `github_token` for `github.com`.
+//! 3. The git credential helpers, via `git credential fill`.
+//! 4. The `gh` CLI, via `gh auth token --hostname <host>`.
+
+use super::{ConfigScope, LayeredConfig, StConfigError};
+use crate::github::GITHUB_HOST;
+use std::{
+    fmt::{self, Display},
+    io::Write,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+/// The source of the GitHub token in use.
+#[derive(Default, Debug, Clone, Eq, PartialEq)]
+pub enum TokenSource {
+    /// No token was found.
+    #[default]
+    Unset,
+    /// The token was configured under `key`, in the layer with the given origin.
+    Config {
+        /// The origin of the configuration layer, e.g. `env:ST_GITHUB_TOKEN`.
+        origin: String,
+        /// The dotted configuration key holding the token.
+        key: String,
+    },
+    /// The token was provided by a git credential helper.
+    GitCredential,
+    /// The token was provided by `gh auth token`.
+    GhCli,
+}
+
+impl Display for TokenSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unset => write!(f, "unset"),
+            Self::Config { origin, .. } => write!(f, "{}", origin),
+            Self::GitCredential => write!(f, "git:git credential fill"),
+            Self::GhCli => write!(f, "gh:gh auth token"),
+        }
+    }
+}
+
+/// Resolves the GitHub token for the given host.
+///
+/// ## Takes
+/// - `host` - The host name of the GitHub instance.
+/// - `layers` - The configuration layers of the repository.
+/// - `dir` - The directory to run `git` and `gh` in, so that repository-level credential helpers
+///   apply.
+///
+/// ## Returns
+/// - `Some((String, TokenSource))` - The token, and where it came from.
+/// - `None` - If no source has a token for the host.
+pub fn resolve_token(
+    host: &str,
+    layers: &LayeredConfig,
+    dir: &Path,
+) -> Result<Option<(String, TokenSource)>, StConfigError> {
+    // The environment overrides every configuration file, and applies to any host.
+    let env_origin = format!("{}:", ConfigScope::Env);
+    let mut candidates = vec![
+        ("github_token".to_string(), true),
+        (format!("hosts.{:?}.token", host), false),
+    ];
+    if host == GITHUB_HOST {
+        candidates.push(("github_token".to_string(), false));
+    }
+
+    for (key, env_only) in candidates {
+        let Some((value, origin)) = layers.get(&key)? else {
+            continue;
+        };
+        if env_only && !origin.starts_with(&env_origin) {
+            continue;
+        }
+        let token = value.as_str().unwrap_or_default().trim();
+        if !token.is_empty() {
+            let token = token.to_string();
+            return Ok(Some((token, TokenSource::Config { origin, key })));
+        }
+    }
+
+    if let Some(token) = git_credential_fill(host, dir) {
+        return Ok(Some((token, TokenSource::GitCredential)));
+    }
+
+    Ok(gh_auth_token(host, dir).map(|token| (token, TokenSource::GhCli)))
+}
+
+/// Asks the git credential helpers for the password of `https://<host>`, without prompting.
+fn git_credential_fill(host: &str, dir: &Path) -> Option<String> {
+    let mut child = Command::new("git")
+        .args(["credential", "fill"])
+        .current_dir(dir)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .env("GIT_ASKPASS", "")
+        .env("SSH_ASKPASS", "")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    child
+        .stdin
+        .take()?
+        .write_all(format!("protocol=https\nhost={}\n\n", host).as_bytes())
+        .ok()?;
+
+    let output = child.wait_with_output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout)
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("password="))
+        .map(|token| token.trim().to_string())
+        .filter(|token| !token.is_empty())
+}
+
+/// Asks the `gh` CLI for its token for the given host.
+fn gh_auth_token(host: &str, dir: &Path) -> Option<String> {
+    let output = Command::new("gh")
+        .args(["auth", "token", "--hostname", host])
+        .current_dir(dir)
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout)
+        .ok()
+        .map(|token| token.trim().to_string())
+        .filter(|token| !token.is_empty())
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use super::{resolve_token, TokenSource};
+    use crate::config::LayeredConfig;
+    use git2::Repository;
+    use std::{fs, os::unix::fs::PermissionsExt};
+
+    /// Creates a repository whose only credential helper answers for `ghe.corp`.
+    fn repository_with_stub_helper() -> (tempfile::TempDir, Repository) {
+        let dir = tempfile::tempdir().unwrap();
+        let repository = Repository::init(dir.path()).unwrap();
+
+        let helper = dir.path().join("credential-helper.sh");
+        fs::write(
+            &helper,
+            "#!/bin/sh\n\
+             [ \"$1\" = get ] || exit 0\n\
+             grep -q '^host=ghe.corp$' || exit 0\n\
+             printf 'username=x-access-token\\npassword=stub-token\\n'\n",
+        )
+        .unwrap();
+        fs::set_permissions(&helper, fs::Permissions::from_mode(0o755)).unwrap();
+
+        // An empty helper resets the helpers configured outside of the repository.
+        let config_path = repository.path().join("config");
+        let mut config = fs::read_to_string(&config_path).unwrap();
+        config.push_str(&format!(
+            "[credential]\n\thelper =\n\thelper = {}\n",
+            helper.display()
+        ));
+        fs::write(&config_path, config).unwrap();
+
+        (dir, repository)
+    }
+
+    #[test]
+    fn falls_back_to_git_credential_helper() {
+        let (dir, _repository) = repository_with_stub_helper();
+        let layers = LayeredConfig::default();
+
+        let (token, source) = resolve_token("ghe.corp", &layers, dir.path())
+            .unwrap()
+            .unwrap();
+        assert_eq!(token, "stub-token");
+        assert_eq!(source, TokenSource::GitCredential);
+    }
+
+    #[test]
+    fn prefers_configured_host_token() {
+        let (dir, _repository) = repository_with_stub_helper();
+        let layers = LayeredConfig::from_tables(&[
+            ("global", "github_token = \"public\""),
+            ("repo", "[hosts.\"ghe.corp\"]\ntoken = \"configured\""),
+        ]);
+
+        let (token, source) = resolve_token("ghe.corp", &layers, dir.path())
+            .unwrap()
+            .unwrap();
+        assert_eq!(token, "configured");
+        assert_eq!(
+            source,
+            TokenSource::Config {
+                origin: "repo".to_string(),
+                key: "hosts.\"ghe.corp\".token".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn environment_overrides_configured_tokens() {
+        let (dir, _repository) = repository_with_stub_helper();
+        let layers = LayeredConfig::from_tables(&[
+            ("repo", "[hosts.\"ghe.corp\"]\ntoken = \"configured\""),
+            ("env:ST_GITHUB_TOKEN", "github_token = \"from-env\""),
+        ]);
+
+        let (token, _) = resolve_token("ghe.corp", &layers, dir.path())
+            .unwrap()
+            .unwrap();
+        assert_eq!(token, "from-env");
+    }
+
+    #[test]
+    fn github_token_only_applies_to_github_com() {
+        let (dir, _repository) = repository_with_stub_helper();
+        let layers = LayeredConfig::from_tables(&[("global", "github_token = \"public\"")]);
+
+        let (token, _) = resolve_token("github.com", &layers, dir.path())
+            .unwrap()
+            .unwrap();
+        assert_eq!(token, "public");
+
+        let (token, source) = resolve_token("ghe.corp", &layers, dir.path())
+            .unwrap()
+            .unwrap();
+        assert_eq!(token, "stub-token");
+        assert_eq!(source, TokenSource::GitCredential);
+    }
+}
@@ -2,15 +2,46 @@
 
 use nu_ansi_term::Color;
 
-/// Name of the `.git` directory.
-pub(crate) const GIT_DIR: &str = ".git";
+/// Name of the directory holding the global config file, within the user's config directory.
+pub(crate) const ST_CFG_DIR_NAME: &str = "st";
 
-/// Name of the global config file.
-pub(crate) const ST_CFG_FILE_NAME: &str = ".st.toml";
+/// Name of the global config file, within [ST_CFG_DIR_NAME].
+pub(crate) const ST_CFG_FILE_NAME: &str = "config.toml";
 
-/// Name of the store file, within `.git`.
+/// Name of the legacy global config file, within the home directory.
+pub(crate) const ST_LEGACY_CFG_FILE_NAME: &str = ".st.toml";
+
+/// Name of the per-repository config file, within the common git directory.
+pub(crate) const ST_REPO_CFG_FILE_NAME: &str = ".st_config.toml";
+
+/// Path of the optional team config file, relative to the root of the repository.
+pub(crate) const ST_TEAM_CFG_PATH: &str = ".st/config.toml";
+
+/// Path of the optional stack comment template, relative to the root of the repository.
+pub(crate) const ST_COMMENT_TEMPLATE_PATH: &str = ".st/stack_comment.md";
+
+/// The maximum number of requests to the GitHub API that are in flight at once.
+pub(crate) const GITHUB_MAX_CONCURRENT_REQUESTS: usize = 8;
+
+/// Prefix of the environment variables that override configuration values.
+pub(crate) const ST_ENV_PREFIX: &str = "ST_";
+
+/// Name of the remote used when none is configured.
+pub(crate) const DEFAULT_REMOTE_NAME: &str = "origin";
+
+/// Name of the store file, within the common git directory.
 pub(crate) const ST_CTX_FILE_NAME: &str = ".st_store.toml";
 
+/// Name of the file caching the state of pull requests, within the common git directory. The cache
+/// is never shared, as every clone refreshes it independently.
+pub(crate) const ST_PR_CACHE_FILE_NAME: &str = ".st_pr_cache.toml";
+
+/// Name of the ref that holds the shared stack metadata commit.
+pub(crate) const ST_METADATA_REF: &str = "refs/st/metadata";
+
+/// Name of the file containing the serialized stack tree, within the metadata commit's tree.
+pub(crate) const ST_METADATA_FILE_NAME: &str = "stack.toml";
+
 /// Array of colors used for displaying stacks in the terminal.
 pub(crate) const COLORS: [Color; 6] = [
     Color::Blue,
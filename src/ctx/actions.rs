@@ -4,10 +4,12 @@ use super::StContext;
 use crate::{
     errors::{StError, StResult},
     git::RepositoryExt,
+    github::{GitHubClient, PullRequestState, PullRequestStatus},
+    tree::{unix_timestamp, CachedPullRequest},
 };
-use git2::BranchType;
+use git2::{BranchType, Repository};
 use nu_ansi_term::Color;
-use octocrab::{models::IssueState, pulls::PullRequestHandler};
+use std::collections::HashMap;
 
 impl<'a> StContext<'a> {
     /// Restacks the branch onto the parent branch passed.
@@ -24,8 +26,27 @@ impl<'a> StContext<'a> {
             return Ok(());
         }
 
-        // Rebase the branch onto its parent.
-        if let Err(e) = self.repository.rebase_branch_onto(branch_name, parent_name) {
+        // Branches that are checked out in another worktree are rebased within that worktree. If
+        // the rebase fails, it is aborted so that the other worktree is left untouched.
+        if let Some(path) = self.repository.checked_out_elsewhere(branch_name)? {
+            let worktree = Repository::open(&path)?;
+            if !worktree.is_working_tree_clean()? {
+                eprintln!("Working tree at `{}` is dirty.", path.display());
+                return Err(StError::WorktreeRestackFailed(
+                    branch_name.to_string(),
+                    path,
+                ));
+            }
+
+            if let Err(e) = worktree.rebase_branch_onto(branch_name, parent_name) {
+                eprintln!("{}", e);
+                worktree.abort_rebase()?;
+                return Err(StError::WorktreeRestackFailed(
+                    branch_name.to_string(),
+                    path,
+                ));
+            }
+        } else if let Err(e) = self.repository.rebase_branch_onto(branch_name, parent_name) {
             eprintln!(
                 "Failed to rebase branch `{}` onto `{}`",
                 Color::Green.paint(branch_name),
@@ -77,41 +98,85 @@ impl<'a> StContext<'a> {
     pub async fn delete_closed_branches(
         &mut self,
         branches: &[String],
-        pulls: &mut PullRequestHandler<'_>,
+        gh_client: &GitHubClient,
     ) -> StResult<usize> {
+        // Fetch the PRs of the branches at once, then prompt in order.
+        let statuses = self.refresh_pull_requests(gh_client, branches).await?;
+
         let mut num_closed = 0;
         for branch in branches.iter() {
-            let tracked_branch = self
-                .tree
-                .get(branch)
-                .ok_or_else(|| StError::BranchNotTracked(branch.clone()))?;
-
-            if let Some(remote_meta) = tracked_branch.remote.as_ref() {
-                let remote_pr = pulls.get(remote_meta.pr_number).await?;
-                let pr_state = remote_pr.state.ok_or(StError::PullRequestNotFound)?;
-
-                if matches!(pr_state, IssueState::Closed) || remote_pr.merged_at.is_some() {
-                    let confirm = inquire::Confirm::new(
-                        format!(
-                            "Pull request for branch `{}` is {}. Would you like to delete the local branch?",
-                            Color::Green.paint(branch),
-                            Color::Purple.bold().paint("closed")
-                        )
-                        .as_str(),
-                    )
-                    .with_default(false)
-                    .prompt()?;
+            let Some(status) = statuses.get(branch) else {
+                continue;
+            };
+            if matches!(
+                status.state,
+                PullRequestState::Closed | PullRequestState::Merged
+            ) {
+                // Branches checked out in another worktree cannot be deleted from here.
+                if let Some(path) = self.repository.checked_out_elsewhere(branch)? {
+                    println!(
+                        "Pull request for branch `{}` is {}, but it is checked out in the worktree at `{}`. Skipping deletion.",
+                        Color::Green.paint(branch),
+                        Color::Purple.bold().paint("closed"),
+                        path.display()
+                    );
+                    continue;
+                }
 
-                    if confirm {
-                        self.delete_branch(branch, true)?;
-                        num_closed += 1;
-                    }
+                let confirm = inquire::Confirm::new(
+                    format!(
+                        "Pull request for branch `{}` is {}. Would you like to delete the local branch?",
+                        Color::Green.paint(branch),
+                        Color::Purple.bold().paint("closed")
+                    )
+                    .as_str(),
+                )
+                .with_default(false)
+                .prompt()?;
+
+                if confirm {
+                    self.delete_branch(branch, true)?;
+                    num_closed += 1;
                 }
             }
         }
         Ok(num_closed)
     }
 
+    /// Fetches the status of the PRs of the given branches, and caches it in their
+    /// [RemoteMetadata].
+    ///
+    /// ## Takes
+    /// - `gh_client` - The client of the repository.
+    /// - `branches` - The branches whose PRs to fetch. Branches without a PR are skipped.
+    ///
+    /// ## Returns
+    /// - `Result<HashMap<String, PullRequestStatus>>` - The status of each submitted branch.
+    ///
+    /// [RemoteMetadata]: crate::tree::RemoteMetadata
+    pub async fn refresh_pull_requests(
+        &mut self,
+        gh_client: &GitHubClient,
+        branches: &[String],
+    ) -> StResult<HashMap<String, PullRequestStatus>> {
+        let submitted = branches
+            .iter()
+            .filter_map(|b| Some((b.clone(), self.tree.get(b)?.remote.as_ref()?.pr_number)))
+            .collect::<Vec<_>>();
+        let numbers = submitted.iter().map(|(_, n)| *n).collect::<Vec<_>>();
+        let statuses = gh_client.pull_request_statuses(&numbers).await?;
+
+        let now = unix_timestamp();
+        let mut by_branch = HashMap::new();
+        for ((branch, _), status) in submitted.into_iter().zip(statuses) {
+            if let Some(remote) = self.tree.get_mut(&branch).and_then(|b| b.remote.as_mut()) {
+                remote.cache = Some(CachedPullRequest::new(&status, now));
+            }
+            by_branch.insert(branch, status);
+        }
+        Ok(by_branch)
+    }
+
     /// Asks the user for confirmation before deleting a branch.
     pub fn delete_branch(
         &mut self,
@@ -121,10 +186,16 @@ impl<'a> StContext<'a> {
         // Ensure the user does not:
         // 1. Attempt to delete the trunk branch.
         // 2. Attempt to delete an untracked branch.
-        if branch_name == self.tree.trunk_name {
+        // 3. Attempt to delete a branch that is checked out in another worktree.
+        if self.tree.is_trunk(branch_name) {
             return Err(StError::CannotDeleteTrunkBranch);
         } else if self.tree.get(branch_name).is_none() {
             return Err(StError::BranchNotTracked(branch_name.to_string()));
+        } else if let Some(path) = self.repository.checked_out_elsewhere(branch_name)? {
+            return Err(StError::BranchCheckedOutInWorktree(
+                branch_name.to_string(),
+                path,
+            ));
         }
 
         // Ask for confirmation to prevent accidental deletion of local refs.
@@ -146,9 +217,17 @@ impl<'a> StContext<'a> {
             return Ok(());
         }
 
-        // Check out the trunk branch prior to deletion.
-        self.repository
-            .checkout_branch(self.tree.trunk_name.as_str())?;
+        // Move off of the branch prior to deletion, if it is checked out in this worktree. The
+        // trunk that the branch descends from is checked out, or `HEAD` is detached at that trunk
+        // if it is checked out in another worktree.
+        if self.repository.current_branch_name().ok().as_deref() == Some(branch_name) {
+            let trunk_name = self.tree.trunk_of(branch_name)?;
+            if self.repository.checked_out_elsewhere(trunk_name)?.is_some() {
+                self.repository.checkout_detached(trunk_name)?;
+            } else {
+                self.repository.checkout_branch(trunk_name)?;
+            }
+        }
 
         // Delete the selected branch.
         self.repository
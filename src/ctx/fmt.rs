@@ -10,7 +10,10 @@ use crate::{
     git::RepositoryExt,
 };
 use nu_ansi_term::Color;
-use std::fmt::{Display, Write};
+use std::{
+    collections::HashMap,
+    fmt::{Display, Write},
+};
 
 impl StContext<'_> {
     /// Gathers an in-order list of [DisplayBranch]es, containing the log-line and branch name.
@@ -40,22 +43,41 @@ impl StContext<'_> {
 
     /// Prints the tree of branches contained within the [StContext].
     pub fn print_tree(&self) -> StResult<()> {
+        self.print_annotated_tree(&HashMap::new())
+    }
+
+    /// Prints the tree of branches contained within the [StContext], followed by the annotation
+    /// of each branch that has one.
+    pub fn print_annotated_tree(&self, annotations: &HashMap<String, String>) -> StResult<()> {
         let mut buf = String::new();
-        self.write_tree(&mut buf)?;
+        self.write_annotated_tree(&mut buf, annotations)?;
         print!("{}", buf);
         Ok(())
     }
 
     /// Writes the tree of branches contained within the [StContext] to the given [Write]r.
     pub fn write_tree<W: Write>(&self, w: &mut W) -> StResult<()> {
-        let trunk_name = self.tree.trunk_name.as_str();
-        self.write_tree_recursive(w, trunk_name, 0, "", "", true)
+        self.write_annotated_tree(w, &HashMap::new())
+    }
+
+    /// Writes the tree of branches contained within the [StContext] to the given [Write]r,
+    /// followed by the annotation of each branch that has one.
+    pub fn write_annotated_tree<W: Write>(
+        &self,
+        w: &mut W,
+        annotations: &HashMap<String, String>,
+    ) -> StResult<()> {
+        self.tree.trunks().into_iter().try_for_each(|trunk_name| {
+            self.write_tree_recursive(w, annotations, trunk_name, 0, "", "", true)
+        })
     }
 
     /// Writes the tree of branches to the given writer recursively.
+    #[allow(clippy::too_many_arguments)]
     fn write_tree_recursive<W: Write>(
         &self,
         w: &mut W,
+        annotations: &HashMap<String, String>,
         branch: &str,
         depth: usize,
         prefix: &str,
@@ -85,18 +107,25 @@ impl StContext<'_> {
             };
             let pull_request = current
                 .remote
+                .as_ref()
                 .map(|r| {
                     let (owner, repo) = self.owner_and_repository()?;
-                    Ok::<_, StError>(Color::Purple.italic().paint(format!(
-                        "https://github.com/{}/{}/pull/{}",
-                        owner, repo, r.pr_number
+                    let host = self.github_host()?;
+                    Ok::<_, StError>(Color::Purple.italic().paint(host.pull_request_url(
+                        &owner,
+                        &repo,
+                        r.pr_number,
                     )))
                 })
                 .transpose()?;
+            let annotation = annotations
+                .get(branch)
+                .map(|a| format!(" ({})", Color::Yellow.italic().paint(a)));
             format!(
-                "{}{}",
+                "{}{}{}",
                 needs_restack,
-                pull_request.map_or(String::new(), |s| format!(" ({})", s))
+                pull_request.map_or(String::new(), |s| format!(" ({})", s)),
+                annotation.unwrap_or_default()
             )
         };
 
@@ -135,6 +164,7 @@ impl StContext<'_> {
             // Write the child and any of its children to the writer.
             self.write_tree_recursive(
                 w,
+                annotations,
                 child,
                 depth + 1,
                 prefix.as_str(),
@@ -0,0 +1,238 @@
+//! Persistence of the [StackTree] within git refs, allowing stack metadata to be shared between
+//! clones of a repository.
+
+use super::{prune_tree, StContext};
+use crate::{
+    constants::{ST_METADATA_FILE_NAME, ST_METADATA_REF},
+    errors::{StError, StResult},
+    git::RepositoryExt,
+    tree::StackTree,
+};
+use git2::{BranchType, ErrorCode, FileMode, Oid, Repository, Signature};
+use nu_ansi_term::Color;
+
+/// Returns the name of the ref that tracks the stack metadata of the given remote.
+fn remote_metadata_ref(remote_name: &str) -> String {
+    format!("refs/st/remotes/{}/metadata", remote_name)
+}
+
+/// Reads the [StackTree] stored in the metadata commit that `ref_name` points to.
+///
+/// ## Takes
+/// - `repository` - The repository to read the metadata from.
+/// - `ref_name` - The name of the ref pointing to the metadata commit.
+///
+/// ## Returns
+/// - `Some((Oid, StackTree))` - The metadata commit, and the [StackTree] stored within it.
+/// - `None` - If the ref does not exist.
+pub fn read_metadata_ref(
+    repository: &Repository,
+    ref_name: &str,
+) -> StResult<Option<(Oid, StackTree)>> {
+    let reference = match repository.find_reference(ref_name) {
+        Ok(reference) => reference,
+        Err(e) if e.code() == ErrorCode::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let commit = reference.peel_to_commit()?;
+    Ok(Some((
+        commit.id(),
+        read_metadata_commit(repository, commit.id())?,
+    )))
+}
+
+/// Reads the [StackTree] stored in the metadata commit with the given [Oid].
+fn read_metadata_commit(repository: &Repository, oid: Oid) -> StResult<StackTree> {
+    let commit = repository.find_commit(oid)?;
+    let entry = commit
+        .tree()?
+        .get_name(ST_METADATA_FILE_NAME)
+        .ok_or_else(|| {
+            StError::DecodingError(format!(
+                "Metadata commit `{}` does not contain `{}`.",
+                oid, ST_METADATA_FILE_NAME
+            ))
+        })?
+        .id();
+    let blob = repository.find_blob(entry)?;
+    let contents =
+        std::str::from_utf8(blob.content()).map_err(|e| StError::DecodingError(e.to_string()))?;
+    Ok(toml::from_str(contents)?)
+}
+
+/// Writes a metadata commit containing the serialized [StackTree] on top of the given parents.
+///
+/// If there is a single parent with identical contents, no commit is created and the parent's
+/// [Oid] is returned.
+fn write_metadata_commit(
+    repository: &Repository,
+    tree: &StackTree,
+    parents: &[Oid],
+    message: &str,
+) -> StResult<Oid> {
+    let blob = repository.blob(toml::to_string_pretty(tree)?.as_bytes())?;
+    let mut builder = repository.treebuilder(None)?;
+    builder.insert(ST_METADATA_FILE_NAME, blob, FileMode::Blob.into())?;
+    let git_tree = repository.find_tree(builder.write()?)?;
+
+    let parents = parents
+        .iter()
+        .map(|oid| repository.find_commit(*oid))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Skip empty commits.
+    if let [parent] = parents.as_slice() {
+        if parent.tree_id() == git_tree.id() {
+            return Ok(parent.id());
+        }
+    }
+
+    let signature = repository
+        .signature()
+        .or_else(|_| Signature::now("st", "st@localhost"))?;
+    let oid = repository.commit(
+        None,
+        &signature,
+        &signature,
+        message,
+        &git_tree,
+        parents.iter().collect::<Vec<_>>().as_slice(),
+    )?;
+    Ok(oid)
+}
+
+impl StContext<'_> {
+    /// Records the current [StackTree] in the shared metadata ref, if it has changed.
+    ///
+    /// Entries for branches that do not exist in this clone are preserved, so that recording the
+    /// local tree never discards metadata written by other clones.
+    ///
+    /// ## Returns
+    /// - `Result<Oid>` - The metadata commit that [ST_METADATA_REF] points to.
+    pub fn snapshot_metadata(&self) -> StResult<Oid> {
+        let (parent, tree) = match read_metadata_ref(self.repository, ST_METADATA_REF)? {
+            Some((oid, shared)) => {
+                let base = self.shared_base.clone().unwrap_or_default();
+                (Some(oid), StackTree::merge(&base, &self.tree, &shared).0)
+            }
+            None => (None, self.tree.clone()),
+        };
+
+        let oid = write_metadata_commit(
+            self.repository,
+            &tree,
+            parent.as_slice(),
+            "st: update stack metadata",
+        )?;
+        if parent != Some(oid) {
+            self.repository
+                .reference(ST_METADATA_REF, oid, true, "st: update stack metadata")?;
+        }
+        Ok(oid)
+    }
+
+    /// Records the current [StackTree] in the shared metadata ref, and pushes it to the remote.
+    ///
+    /// ## Takes
+    /// - `remote_name` - The name of the remote to push the metadata to.
+    pub fn push_metadata(&self, remote_name: &str) -> StResult<()> {
+        self.snapshot_metadata()?;
+        self.repository
+            .push_refspec(&format!("{0}:{0}", ST_METADATA_REF), remote_name)?;
+        Ok(())
+    }
+
+    /// Fetches the shared metadata from the remote, and merges it into the local [StackTree].
+    ///
+    /// Concurrent edits are merged branch by branch, with the local version of a branch winning
+    /// if both sides changed it. Branches that are missing locally are restored from their
+    /// remote-tracking refs when possible.
+    ///
+    /// ## Takes
+    /// - `remote_name` - The name of the remote to pull the metadata from.
+    ///
+    /// ## Returns
+    /// - `Result<bool>` - `false` if the remote has no stack metadata, `true` otherwise.
+    pub fn pull_metadata(&mut self, remote_name: &str) -> StResult<bool> {
+        let tracking_ref = remote_metadata_ref(remote_name);
+        self.repository.fetch_refspec(
+            &format!("+refs/st/*:refs/st/remotes/{}/*", remote_name),
+            remote_name,
+        )?;
+        let Some((theirs_oid, theirs)) = read_metadata_ref(self.repository, &tracking_ref)? else {
+            return Ok(false);
+        };
+
+        // Record local changes first, so that they take part in the merge.
+        let ours_oid = self.snapshot_metadata()?;
+        let ours = read_metadata_commit(self.repository, ours_oid)?;
+
+        let (merged_oid, merged) = if ours_oid == theirs_oid
+            || self.repository.graph_descendant_of(ours_oid, theirs_oid)?
+        {
+            (ours_oid, ours)
+        } else if self.repository.graph_descendant_of(theirs_oid, ours_oid)? {
+            (theirs_oid, theirs)
+        } else {
+            let base = match self.repository.merge_base(ours_oid, theirs_oid) {
+                Ok(base) => read_metadata_commit(self.repository, base)?,
+                Err(e) if e.code() == ErrorCode::NotFound => StackTree::default(),
+                Err(e) => return Err(e.into()),
+            };
+
+            let (merged, conflicts) = StackTree::merge(&base, &ours, &theirs);
+            conflicts.iter().for_each(|branch| {
+                println!(
+                    "Branch `{}` was changed both locally and on `{}`. Keeping the local version.",
+                    Color::Green.paint(branch),
+                    Color::Blue.paint(remote_name)
+                );
+            });
+
+            let message = format!("st: merge stack metadata from `{}`", remote_name);
+            let oid =
+                write_metadata_commit(self.repository, &merged, &[ours_oid, theirs_oid], &message)?;
+            (oid, merged)
+        };
+        self.repository
+            .reference(ST_METADATA_REF, merged_oid, true, "st: pull stack metadata")?;
+
+        // Adopt the merged tree, restoring branches that only exist on the remote. The cached pull
+        // request state is local, so it is carried over.
+        let cache = self.tree.pull_request_cache();
+        self.tree = merged;
+        self.tree.attach_pull_request_cache(&cache);
+        self.restore_remote_branches(remote_name)?;
+        prune_tree(self.repository, &mut self.tree)?;
+        self.shared_base = Some(self.tree.clone());
+
+        Ok(true)
+    }
+
+    /// Creates local branches for tracked branches that only exist as remote-tracking branches.
+    fn restore_remote_branches(&self, remote_name: &str) -> StResult<()> {
+        for branch in self.tree.branches.keys() {
+            if self
+                .repository
+                .find_branch(branch, BranchType::Local)
+                .is_ok()
+            {
+                continue;
+            }
+
+            let remote_ref = format!("refs/remotes/{}/{}", remote_name, branch);
+            let Ok(reference) = self.repository.find_reference(&remote_ref) else {
+                continue;
+            };
+
+            self.repository
+                .branch(branch, &reference.peel_to_commit()?, false)?;
+            println!(
+                "Restored branch `{}` from `{}`.",
+                Color::Green.paint(branch),
+                Color::Blue.paint(format!("{}/{}", remote_name, branch))
+            );
+        }
+        Ok(())
+    }
+}
@@ -1,40 +1,66 @@
 //! The in-memory context of the `st` application.
 
 use crate::{
-    config::StConfig,
-    constants::{GIT_DIR, ST_CTX_FILE_NAME},
+    config::{MetadataBackend, StConfig},
+    constants::{DEFAULT_REMOTE_NAME, ST_CTX_FILE_NAME, ST_METADATA_REF, ST_PR_CACHE_FILE_NAME},
     errors::{StError, StResult},
-    tree::StackTree,
+    git::RepositoryExt,
+    github::{GitHubClient, GitHubHost},
+    remote_url::RemoteUrl,
+    tree::{CachedPullRequest, StackTree},
 };
 use git2::{BranchType, Repository};
-use std::path::PathBuf;
+use metadata::read_metadata_ref;
+use std::{collections::BTreeMap, path::PathBuf};
 
 mod actions;
 mod fmt;
+mod metadata;
 mod stack_management;
 
 /// Returns the path to the persistent application context for the given [Repository].
 ///
+/// The context lives in the common git directory, so that all worktrees of a repository share a
+/// single stack tree.
+///
 /// ## Takes
 /// - `repository` - The repository to get the context path for.
 ///
 /// ## Returns
-/// - `Some(PathBuf)` - The path to the serialized context.
-/// - `None` - If the repository does not have a workdir.
-pub fn ctx_path(repository: &Repository) -> Option<PathBuf> {
-    repository
-        .workdir()
-        .map(|p| p.join(GIT_DIR).join(ST_CTX_FILE_NAME))
+/// - `PathBuf` - The path to the serialized context.
+pub fn ctx_path(repository: &Repository) -> PathBuf {
+    repository.common_dir().join(ST_CTX_FILE_NAME)
+}
+
+/// Returns the path to the local cache of pull request state for the given [Repository].
+///
+/// The cache is kept apart from the stack tree, so that refreshing it never changes the shared
+/// metadata.
+///
+/// ## Takes
+/// - `repository` - The repository to get the cache path for.
+///
+/// ## Returns
+/// - `PathBuf` - The path to the serialized cache.
+pub fn pr_cache_path(repository: &Repository) -> PathBuf {
+    repository.common_dir().join(ST_PR_CACHE_FILE_NAME)
 }
 
 /// The in-memory context of the `st` application.
 pub struct StContext<'a> {
-    /// The global configuration for `st`.
+    /// The configuration for `st`, resolved for the repository.
     pub cfg: StConfig,
     /// The repository associated with the store.
     pub repository: &'a Repository,
     /// The tree of branches tracked by `st`.
     pub tree: StackTree,
+    /// The shared stack metadata as of the last load or pull, restricted to local branches.
+    ///
+    /// Used as the merge base when recording the local tree in [ST_METADATA_REF].
+    shared_base: Option<StackTree>,
+    /// The tracked branches that were untracked on load, as they no longer exist in the
+    /// repository.
+    pruned: Vec<String>,
 }
 
 impl<'a> StContext<'a> {
@@ -44,89 +70,238 @@ impl<'a> StContext<'a> {
             cfg,
             repository,
             tree: StackTree::new(trunk),
+            shared_base: None,
+            pruned: Vec::new(),
         }
     }
 
     /// Loads the [StackTree] for the given [Repository], and assembles a [StContext].
     pub fn try_load(cfg: StConfig, repository: &'a Repository) -> StResult<Option<Self>> {
-        let store_path = ctx_path(repository).ok_or(StError::GitRepositoryRootNotFound)?;
+        // Load the shared metadata, if it has been recorded.
+        let shared = read_metadata_ref(repository, ST_METADATA_REF)?.map(|(_, tree)| tree);
 
-        // If the store doesn't exist, return None.
-        if !store_path.exists() {
-            return Ok(None);
-        }
+        // Load the stack from the configured backend. The ref backend falls back to the store
+        // file, so that switching backends does not lose the existing stack.
+        let stack = match (cfg.metadata_backend, shared.clone()) {
+            (MetadataBackend::Ref, Some(shared)) => shared,
+            _ => {
+                let store_path = ctx_path(repository);
+
+                // If the store doesn't exist, return None.
+                if !store_path.exists() {
+                    return Ok(None);
+                }
+
+                toml::from_str(&std::fs::read_to_string(store_path)?)?
+            }
+        };
 
-        let stack: StackTree = toml::from_str(&std::fs::read_to_string(store_path)?)?;
+        let shared_base = shared
+            .map(|mut shared| {
+                prune_tree(repository, &mut shared)?;
+                Ok::<_, StError>(shared)
+            })
+            .transpose()?;
         let mut store_with_repo = Self {
             cfg,
             repository,
             tree: stack,
+            shared_base,
+            pruned: Vec::new(),
         };
-        store_with_repo.prune()?;
+        store_with_repo.pruned = store_with_repo.prune()?;
+
+        // The cache is disposable, so a missing or unreadable cache is ignored.
+        let cache = std::fs::read_to_string(pr_cache_path(repository))
+            .ok()
+            .and_then(|cache| toml::from_str::<BTreeMap<String, CachedPullRequest>>(&cache).ok())
+            .unwrap_or_default();
+        store_with_repo.tree.attach_pull_request_cache(&cache);
 
         Ok(Some(store_with_repo))
     }
 
-    /// Parses the GitHub owner and repository from the current repository's remote URL.
+    /// Returns the name of the remote that hosts the repository on GitHub.
+    pub fn remote_name(&self) -> &str {
+        self.cfg.remote.as_deref().unwrap_or(DEFAULT_REMOTE_NAME)
+    }
+
+    /// Returns the name of the remote that branches are pushed to. Differs from
+    /// [Self::remote_name] when contributing from a fork.
+    pub fn push_remote_name(&self) -> &str {
+        self.cfg
+            .push_remote
+            .as_deref()
+            .unwrap_or(self.remote_name())
+    }
+
+    /// Parses the GitHub owner and repository that pull requests are opened against.
     pub fn owner_and_repository(&self) -> StResult<(String, String)> {
-        let remote = self.repository.find_remote("origin")?;
-        let url = remote
-            .url()
-            .ok_or(StError::RemoteNotFound("origin".to_string()))?;
-
-        let (org, repo) = if url.starts_with("git@") {
-            // Handle SSH URL: git@github.com:org/repo.git
-            let parts = url.split(':').collect::<Vec<_>>();
-            let repo_parts = parts
-                .get(1)
-                .ok_or(StError::DecodingError(
-                    "Invalid SSH URL format.".to_string(),
-                ))?
-                .split('/')
-                .collect::<Vec<_>>();
-            let org = repo_parts.first().ok_or(StError::DecodingError(
-                "Organization not found.".to_string(),
-            ))?;
-            let repo = repo_parts.get(1).ok_or(StError::DecodingError(
-                "Repository not found while decoding remote URL.".to_string(),
-            ))?;
-            (org.to_string(), repo.trim_end_matches(".git").to_string())
-        } else if url.starts_with("https://") {
-            // Handle HTTPS URL: https://github.com/org/repo.git
-            let parts = url.split('/').collect::<Vec<_>>();
-            let org = parts.get(parts.len() - 2).ok_or(StError::DecodingError(
-                "Organization not found.".to_string(),
-            ))?;
-            let repo = parts.last().ok_or(StError::DecodingError(
-                "Repository not found while decoding remote URL.".to_string(),
-            ))?;
-            (org.to_string(), repo.trim_end_matches(".git").to_string())
-        } else {
-            return Err(StError::DecodingError(
-                "Unsupported remote URL format.".to_string(),
-            ));
-        };
+        let url = RemoteUrl::from_remote(self.repository, self.remote_name())?;
+        Ok((url.owner, url.repo))
+    }
+
+    /// Parses the GitHub owner and repository of the fork that branches are pushed to, if branches
+    /// are pushed to a different repository than the one pull requests are opened against.
+    pub fn fork_owner_and_repository(&self) -> StResult<Option<(String, String)>> {
+        let upstream = self.owner_and_repository()?;
+        let url = RemoteUrl::from_push_remote(self.repository, self.push_remote_name())?;
+        let fork = (url.owner, url.repo);
+        Ok((fork != upstream).then_some(fork))
+    }
+
+    /// Resolves the endpoints of the GitHub instance that pull requests are opened against.
+    pub fn github_host(&self) -> StResult<GitHubHost> {
+        let url = RemoteUrl::from_remote(self.repository, self.remote_name())?;
+        Ok(GitHubHost::resolve(&url.host, &self.cfg))
+    }
 
-        Ok((org, repo))
+    /// Builds an API client for the repository that pull requests are opened against.
+    pub fn github_client(&self) -> StResult<GitHubClient> {
+        let (owner, repo) = self.owner_and_repository()?;
+        GitHubClient::new(&self.github_host()?, &self.cfg.github_token, owner, repo)
+    }
+
+    /// Returns the tracked branches that were untracked when the context was loaded, as they no
+    /// longer exist in the git repository.
+    pub fn pruned_branches(&self) -> &[String] {
+        &self.pruned
     }
 
     /// Prunes branches in the context that no longer exist in the git repository.
-    fn prune(&mut self) -> StResult<()> {
-        let branches = self.tree.branches()?;
-        branches.iter().try_for_each(|b| {
-            if self.repository.find_branch(b, BranchType::Local).is_err() {
-                self.tree.delete(b)?;
+    ///
+    /// ## Returns
+    /// - `Result<Vec<String>>` - The names of the pruned branches.
+    fn prune(&mut self) -> StResult<Vec<String>> {
+        prune_tree(self.repository, &mut self.tree)
+    }
+
+    /// Persists the [StackTree] to the configured [MetadataBackend].
+    fn persist(&self) -> StResult<()> {
+        match self.cfg.metadata_backend {
+            MetadataBackend::File => {
+                std::fs::write(
+                    ctx_path(self.repository),
+                    toml::to_string_pretty(&self.tree)?,
+                )?;
+            }
+            MetadataBackend::Ref => {
+                self.snapshot_metadata()?;
             }
-            Ok::<_, StError>(())
-        })
+        }
+
+        // Persist the cache of pull request state, without creating an empty cache file.
+        let cache = self.tree.pull_request_cache();
+        let cache_path = pr_cache_path(self.repository);
+        if !cache.is_empty() || cache_path.exists() {
+            std::fs::write(cache_path, toml::to_string_pretty(&cache)?)?;
+        }
+        Ok(())
+    }
+}
+
+/// Prunes branches in the [StackTree] that no longer exist in the git repository. Trunk branches
+/// are never pruned.
+///
+/// ## Returns
+/// - `Result<Vec<String>>` - The names of the pruned branches.
+fn prune_tree(repository: &Repository, tree: &mut StackTree) -> StResult<Vec<String>> {
+    let mut pruned = Vec::new();
+    for b in tree.branches()? {
+        if !tree.is_trunk(&b) && repository.find_branch(&b, BranchType::Local).is_err() {
+            tree.delete(&b)?;
+            pruned.push(b);
+        }
     }
+    Ok(pruned)
 }
 
 impl Drop for StContext<'_> {
     fn drop(&mut self) {
         // Persist the store on drop.
-        let store_path = ctx_path(self.repository).expect("Failed to get context path.");
-        let store = toml::to_string_pretty(&self.tree).expect("Failed to serialize context.");
-        std::fs::write(store_path, store).expect("Failed to persist context to disk.");
+        self.persist().expect("Failed to persist context.");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{pr_cache_path, StContext};
+    use crate::{
+        config::{MetadataBackend, StConfig},
+        constants::ST_METADATA_REF,
+        github::{
+            status::{CheckStatus, Mergeability, ReviewStatus},
+            PullRequestState,
+        },
+        tree::{CachedPullRequest, RemoteMetadata},
+    };
+    use git2::{Repository, Signature};
+
+    #[test]
+    fn refreshing_cache_keeps_metadata_ref() {
+        let dir = tempfile::tempdir().unwrap();
+        let repository = Repository::init(dir.path()).unwrap();
+        let signature = Signature::now("st", "st@example.com").unwrap();
+        let tree_oid = repository.treebuilder(None).unwrap().write().unwrap();
+        let tree = repository.find_tree(tree_oid).unwrap();
+        let oid = repository
+            .commit(
+                Some("refs/heads/main"),
+                &signature,
+                &signature,
+                "base",
+                &tree,
+                &[],
+            )
+            .unwrap();
+        repository
+            .branch("a", &repository.find_commit(oid).unwrap(), false)
+            .unwrap();
+
+        let cfg = StConfig {
+            metadata_backend: MetadataBackend::Ref,
+            ..Default::default()
+        };
+        let mut ctx = StContext::fresh(cfg.clone(), &repository, "main".to_string());
+        ctx.tree.insert("main", &oid.to_string(), "a").unwrap();
+        ctx.tree.get_mut("a").unwrap().remote = Some(RemoteMetadata::new(1));
+        drop(ctx);
+        let metadata_oid = repository.refname_to_id(ST_METADATA_REF).unwrap();
+
+        // Refresh the cached state of the pull request.
+        let cache = CachedPullRequest {
+            title: "Add a".to_string(),
+            state: PullRequestState::Open,
+            review: ReviewStatus::None,
+            checks: CheckStatus::Pending,
+            failing_checks: Vec::new(),
+            mergeable: Mergeability::Unknown,
+            unresolved_threads: None,
+            head_sha: oid.to_string(),
+            fetched_at: 10,
+        };
+        let mut ctx = StContext::try_load(cfg.clone(), &repository)
+            .unwrap()
+            .unwrap();
+        ctx.tree
+            .get_mut("a")
+            .unwrap()
+            .remote
+            .as_mut()
+            .unwrap()
+            .cache = Some(cache.clone());
+        drop(ctx);
+
+        assert_eq!(
+            repository.refname_to_id(ST_METADATA_REF).unwrap(),
+            metadata_oid
+        );
+        assert!(pr_cache_path(&repository).exists());
+
+        let ctx = StContext::try_load(cfg, &repository).unwrap().unwrap();
+        assert_eq!(
+            ctx.tree.get("a").unwrap().remote.as_ref().unwrap().cache,
+            Some(cache)
+        );
     }
 }
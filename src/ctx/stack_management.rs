@@ -13,13 +13,19 @@ impl StContext<'_> {
     ///
     /// The returned stack is ordered from the trunk branch to the tip of the stack.
     pub fn discover_stack(&self) -> StResult<Vec<String>> {
+        self.stack_of(&self.repository.current_branch_name()?)
+    }
+
+    /// Discovers the stack that contains the given branch, including the trunk branch.
+    ///
+    /// The returned stack is ordered from the trunk branch to the tip of the stack, ending at the
+    /// first fork above `current_branch`.
+    pub fn stack_of(&self, current_branch: &str) -> StResult<Vec<String>> {
         let mut stack = VecDeque::new();
 
-        // Get the current branch name.
-        let current_branch = self.repository.current_branch_name()?;
         let current_tracked_branch = self
             .tree
-            .get(&current_branch)
+            .get(current_branch)
             .ok_or_else(|| StError::BranchNotTracked(current_branch.to_string()))?;
 
         // Resolve upstack.
@@ -35,7 +41,7 @@ impl StContext<'_> {
         }
 
         // Push the curent branch onto the stack.
-        stack.push_back(current_branch);
+        stack.push_back(current_branch.to_string());
 
         // Attempt to resolve downstack. If there are multiple children, then the stack is ambiguous,
         // and we end resolution at the fork.
@@ -2,8 +2,84 @@
 
 use crate::{config::StConfigError, git::GitCommandError};
 use nu_ansi_term::Color;
+use std::{
+    fmt::{self, Debug, Display},
+    path::PathBuf,
+    sync::Mutex,
+};
 use thiserror::Error;
 
+/// Prefixes of the tokens issued by GitHub.
+const GITHUB_TOKEN_PREFIXES: &[&str] = &["github_pat_", "ghp_", "gho_", "ghu_", "ghs_", "ghr_"];
+
+/// Secrets in use by the application, which must never appear in error messages.
+static SECRETS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Registers a secret, such as a GitHub token, to be redacted from error messages.
+pub fn register_secret(secret: &str) {
+    let mut secrets = SECRETS.lock().unwrap_or_else(|e| e.into_inner());
+    if !secret.is_empty() && !secrets.iter().any(|s| s == secret) {
+        secrets.push(secret.to_string());
+    }
+}
+
+/// Redacts registered secrets, GitHub tokens and bearer credentials from the given text.
+///
+/// ## Takes
+/// - `text` - The text to redact.
+///
+/// ## Returns
+/// - `String` - The text, with every secret replaced by `<redacted>`.
+pub fn redact_secrets(text: &str) -> String {
+    const REDACTED: &str = "<redacted>";
+
+    let mut redacted = text.to_string();
+    for secret in SECRETS.lock().unwrap_or_else(|e| e.into_inner()).iter() {
+        redacted = redacted.replace(secret.as_str(), REDACTED);
+    }
+
+    // Redact well-known token shapes, in case a token was never registered.
+    for prefix in GITHUB_TOKEN_PREFIXES.iter().chain(&["Bearer "]) {
+        let mut result = String::with_capacity(redacted.len());
+        let mut rest = redacted.as_str();
+        while let Some(index) = rest.find(prefix) {
+            let (before, after) = rest.split_at(index + prefix.len());
+            let end = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.')))
+                .unwrap_or(after.len());
+            if end == 0 {
+                result.push_str(before);
+            } else if *prefix == "Bearer " {
+                // Keep the authentication scheme, redacting only the credential.
+                result.push_str(before);
+                result.push_str(REDACTED);
+            } else {
+                result.push_str(&before[..index]);
+                result.push_str(REDACTED);
+            }
+            rest = &after[end..];
+        }
+        result.push_str(rest);
+        redacted = result;
+    }
+    redacted
+}
+
+/// An [octocrab::Error], formatted with secrets redacted.
+pub struct RedactedOctocrabError(pub octocrab::Error);
+
+impl Debug for RedactedOctocrabError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&redact_secrets(&format!("{:?}", self.0)))
+    }
+}
+
+impl Display for RedactedOctocrabError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
 /// Errors for the `st` application.
 #[derive(Error, Debug)]
 pub enum StError {
@@ -22,6 +98,18 @@ pub enum StError {
     /// Cannot delete the trunk branch.
     #[error("Cannot delete the trunk branch.")]
     CannotDeleteTrunkBranch,
+    /// Cannot remove the default trunk branch.
+    #[error("Cannot remove the default trunk branch.")]
+    CannotRemoveDefaultTrunk,
+    /// The branch is not a trunk branch.
+    #[error("Branch `{}` is not a trunk branch.", Color::Blue.paint(.0))]
+    NotATrunk(String),
+    /// The trunk branch still has branches stacked on top of it.
+    #[error(
+        "Trunk `{}` still has branches stacked on top of it. Delete or untrack them first.",
+        Color::Blue.paint(.0)
+    )]
+    TrunkHasChildren(String),
     /// A branch needs to be restacked.
     #[error(
         "Branch `{}` needs to be restacked before continuing. Restack with `{}` before continuing.",
@@ -46,20 +134,105 @@ pub enum StError {
     /// A remote pull request could not be found.
     #[error("Remote pull request not found.")]
     PullRequestNotFound,
+    /// The requested pull request template does not exist.
+    #[error(
+        "Pull request template `{}` not found. Templates are read from `{}` and `{}` directories.",
+        Color::Blue.paint(.0),
+        Color::Blue.paint("pull_request_template.md"),
+        Color::Blue.paint("PULL_REQUEST_TEMPLATE/")
+    )]
+    PullRequestTemplateNotFound(String),
+    /// The stack comment template resolves to a file outside of the repository.
+    #[error(
+        "The stack comment template `{}` is outside of the repository.",
+        .0.display()
+    )]
+    CommentTemplateOutsideRepository(PathBuf),
+    /// The GitHub GraphQL API returned errors.
+    #[error("🐙 GraphQL error: {}", .0)]
+    GraphQLError(String),
+    /// The GitHub GraphQL API does not support a query, e.g. because a field does not exist on
+    /// an older GitHub Enterprise Server.
+    #[error("🐙 GraphQL schema error: {}", .0)]
+    GraphQLSchemaError(String),
+    /// The configured milestone does not exist.
+    #[error("Milestone `{}` not found among the open milestones of the repository.", Color::Blue.paint(.0))]
+    MilestoneNotFound(String),
+    /// The current stack has no pull request to merge.
+    #[error(
+        "No open pull request at the bottom of the current stack. Submit it first with `{}`.",
+        Color::Blue.paint("st submit")
+    )]
+    NothingToMerge,
+    /// The pull request was closed without being merged.
+    #[error("Pull request #{} was closed without being merged.", .0)]
+    PullRequestClosed(u64),
+    /// GitHub refused to merge the pull request.
+    #[error("Failed to merge pull request #{}: {}", .0, .1)]
+    MergeFailed(u64, String),
+    /// The pull request was not reported as merged in time.
+    #[error("Timed out waiting for pull request #{} to be merged.", .0)]
+    MergeTimedOut(u64),
+    /// Checks of the pull request failed.
+    #[error("Checks of pull request #{} failed: {}", .0, Color::Red.paint(.1))]
+    ChecksFailed(u64, String),
+    /// The checks of the pull request did not settle in time.
+    #[error("Timed out waiting for the checks of pull request #{} to settle.", .0)]
+    ChecksTimedOut(u64),
+    /// The checks of the stack did not settle while watching its status.
+    #[error("Stopped watching after {} minutes, as some checks are still pending.", .0)]
+    WatchTimedOut(u64),
+    /// The file of per-branch pull request metadata could not be parsed.
+    #[error("Invalid pull request metadata file `{}`: {}", .0.display(), .1)]
+    InvalidMetadataFile(PathBuf, String),
 
     // ---- [ Git Errors ] ----
     /// `st` mused be run within a git repository.
     #[error("`{}` must be used within a git repository.", Color::Blue.paint("st"))]
     NotAGitRepository,
-    /// The git repository root could not be found.
-    #[error("Git repository root could not be found.")]
-    GitRepositoryRootNotFound,
     /// Remote not found.
     #[error("Remote `{}` not found.", Color::Blue.paint(.0))]
     RemoteNotFound(String),
+    /// The remote URL points to a local path rather than a hosted repository.
+    #[error("Remote URL `{}` points to a local path, not a hosted repository.", Color::Blue.paint(.0))]
+    LocalRemoteUrl(String),
+    /// The remote URL uses a transport that `st` does not support.
+    #[error(
+        "Remote URL `{}` uses the unsupported `{}` scheme.",
+        Color::Blue.paint(.0),
+        Color::Blue.paint(.1)
+    )]
+    UnsupportedRemoteScheme(String, String),
+    /// The remote URL does not contain a host.
+    #[error("Remote URL `{}` does not contain a host.", Color::Blue.paint(.0))]
+    RemoteUrlMissingHost(String),
+    /// The remote URL contains a port that is not a number.
+    #[error("Remote URL `{}` contains an invalid port.", Color::Blue.paint(.0))]
+    RemoteUrlInvalidPort(String),
+    /// The path of the remote URL does not contain both an owner and a repository.
+    #[error(
+        "Remote URL `{}` does not contain an owner and a repository.",
+        Color::Blue.paint(.0)
+    )]
+    RemoteUrlMissingRepository(String),
     /// The branch was not found in the local git tree.
     #[error("Branch was not found in local git tree.")]
     BranchUnavailable,
+    /// The branch is checked out in another worktree.
+    #[error(
+        "Branch `{}` is checked out in the worktree at `{}`.",
+        Color::Blue.paint(.0),
+        .1.display()
+    )]
+    BranchCheckedOutInWorktree(String, PathBuf),
+    /// A branch could not be restacked within the worktree it is checked out in.
+    #[error(
+        "Failed to restack branch `{}` in the worktree at `{}`. Run `{}` from that worktree to resolve it.",
+        Color::Green.paint(.0),
+        .1.display(),
+        Color::Blue.paint("st restack")
+    )]
+    WorktreeRestackFailed(String, PathBuf),
 
     // ---- [ Child Errors ] ----
     /// An [StConfigError] occurred.
@@ -72,8 +245,8 @@ pub enum StError {
     #[error(transparent)]
     GitCommandError(#[from] GitCommandError),
     /// An [octocrab::Error] occurred.
-    #[error("🐙 octocrab error: {:?}", .0)]
-    OctocrabError(#[from] octocrab::Error),
+    #[error("🐙 octocrab error: {}", .0)]
+    OctocrabError(RedactedOctocrabError),
     /// An [inquire::InquireError] occurred.
     #[error("🔍 inquire error: {}", .0)]
     InquireError(#[from] inquire::InquireError),
@@ -93,3 +266,33 @@ pub enum StError {
 
 /// A short-hand [Result] type alias for the [StError].
 pub type StResult<T> = Result<T, StError>;
+
+impl From<octocrab::Error> for StError {
+    fn from(e: octocrab::Error) -> Self {
+        Self::OctocrabError(RedactedOctocrabError(e))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{redact_secrets, register_secret};
+
+    #[test]
+    fn redacts_tokens() {
+        register_secret("s3cr3t-token-value");
+        let cases = [
+            ("token s3cr3t-token-value leaked", "token <redacted> leaked"),
+            (
+                "Authorization: Bearer abc.def-ghi",
+                "Authorization: Bearer <redacted>",
+            ),
+            ("\"ghp_0123456789abcdef\"", "\"<redacted>\""),
+            ("url?access=github_pat_11AB_cd", "url?access=<redacted>"),
+            ("no secrets here", "no secrets here"),
+        ];
+
+        for (text, expected) in cases {
+            assert_eq!(redact_secrets(text), expected, "{}", text);
+        }
+    }
+}
@@ -2,27 +2,59 @@
 
 use crate::constants::QUOTE_CHAR;
 use git2::{
-    build::CheckoutBuilder, Branch, BranchType, ErrorClass, ErrorCode, Repository, StatusOptions,
+    build::CheckoutBuilder, Branch, BranchType, Commit, ErrorClass, ErrorCode, Repository, Sort,
+    StatusOptions,
 };
 use nu_ansi_term::Color::Red;
-use std::{env, process::Command};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
 use thiserror::Error;
 
 /// Returns the repository for the current working directory, and [None] if
 /// the current working directory is not within a git repository or an error
 /// occurs.
+///
+/// Respects the `GIT_DIR` and `GIT_WORK_TREE` environment variables.
 pub fn active_repository() -> Option<Repository> {
-    Repository::discover(env::current_dir().ok()?).ok()
+    Repository::open_from_env().ok()
 }
 
 /// Extension trait for the [Repository] type to expose helper functions related to
 /// repository management.
 pub trait RepositoryExt {
+    /// Returns the path to the common git directory, which is shared by all worktrees of the
+    /// repository.
+    ///
+    /// ## Returns
+    /// - `PathBuf` - The path to the common git directory.
+    fn common_dir(&self) -> PathBuf;
+
+    /// Returns a map of branch names to the working directories of the worktrees they are checked
+    /// out in, across all worktrees of the repository.
+    ///
+    /// ## Returns
+    /// - `Result<HashMap<String, PathBuf>>` - The checked out branches, or an error.
+    fn worktree_branches(&self) -> Result<HashMap<String, PathBuf>, git2::Error>;
+
+    /// Returns the working directory of the worktree that has `branch_name` checked out, if it is
+    /// not the current worktree.
+    ///
+    /// ## Takes
+    /// - `branch_name` - The name of the branch to look up.
+    ///
+    /// ## Returns
+    /// - `Result<Option<PathBuf>>` - The path of the other worktree, or an error.
+    fn checked_out_elsewhere(&self, branch_name: &str) -> Result<Option<PathBuf>, git2::Error>;
+
     /// Returns the current [Branch].
     ///
     /// ## Returns
     /// - `Result<Branch>` - The current [Branch], or an error.
-    fn current_branch(&self) -> Result<Branch, git2::Error>;
+    fn current_branch(&self) -> Result<Branch<'_>, git2::Error>;
 
     /// Returns the name of the current [Branch].
     ///
@@ -36,6 +68,36 @@ pub trait RepositoryExt {
     /// - `Result<bool>` - True if the working tree is clean, false otherwise.
     fn is_working_tree_clean(&self) -> Result<bool, git2::Error>;
 
+    /// Returns the commits that are reachable from `branch_name` but not from `parent_name`,
+    /// oldest first.
+    ///
+    /// ## Takes
+    /// - `branch_name` - The name of the branch whose commits to list.
+    /// - `parent_name` - The name of the branch that `branch_name` is stacked on.
+    ///
+    /// ## Returns
+    /// - `Result<Vec<Commit>>` - The commits of the branch, or an error.
+    fn branch_commits(
+        &self,
+        branch_name: &str,
+        parent_name: &str,
+    ) -> Result<Vec<Commit<'_>>, git2::Error>;
+
+    /// Returns the paths of the files changed on `branch_name` since it diverged from
+    /// `parent_name`. Renamed files are listed under both paths.
+    ///
+    /// ## Takes
+    /// - `branch_name` - The name of the branch whose changes to list.
+    /// - `parent_name` - The name of the branch that `branch_name` is compared against.
+    ///
+    /// ## Returns
+    /// - `Result<Vec<String>>` - The changed paths, or an error.
+    fn changed_files(
+        &self,
+        branch_name: &str,
+        parent_name: &str,
+    ) -> Result<Vec<String>, git2::Error>;
+
     /// Checks out a branch with the given `branch_name`.
     ///
     /// ## Takes
@@ -46,6 +108,15 @@ pub trait RepositoryExt {
     /// - `Result<()>` - The result of the operation.
     fn checkout_branch(&self, branch_name: &str) -> Result<(), git2::Error>;
 
+    /// Checks out the commit at the tip of `branch_name` with a detached `HEAD`.
+    ///
+    /// ## Takes
+    /// - `branch_name` - The name of the branch whose tip to check out.
+    ///
+    /// ## Returns
+    /// - `Result<()>` - The result of the operation.
+    fn checkout_detached(&self, branch_name: &str) -> Result<(), git2::Error>;
+
     /// Set the target of a branch to its upstream [git2::Reference].
     ///
     /// ## Takes
@@ -69,6 +140,25 @@ pub trait RepositoryExt {
     /// - `Result<()>` - The result of the operation.
     fn rebase_branch_onto(&self, branch_name: &str, onto: &str) -> Result<(), GitCommandError>;
 
+    /// Rebases the commits of a branch that are not reachable from `upstream` onto another
+    /// branch, i.e. `git rebase --onto <onto> <upstream> <branch>`. Unlike
+    /// [Self::rebase_branch_onto], commits that were rewritten upstream, e.g. by a squash merge,
+    /// are left out.
+    ///
+    /// ## Takes
+    /// - `branch_name` - The name of the branch to rebase.
+    /// - `onto_name` - The name of the branch to rebase onto.
+    /// - `upstream` - The commit that the branch was previously based on.
+    ///
+    /// ## Returns
+    /// - `Result<()>` - The result of the operation.
+    fn rebase_branch_range_onto(
+        &self,
+        branch_name: &str,
+        onto_name: &str,
+        upstream: &str,
+    ) -> Result<(), GitCommandError>;
+
     /// Aborts a rebase in progress.
     ///
     /// ## Returns
@@ -100,10 +190,84 @@ pub trait RepositoryExt {
     /// ## Returns
     /// - `Result<()>` - The result of the operation.
     fn pull_branch(&self, branch_name: &str, remote_name: &str) -> Result<(), GitCommandError>;
+
+    /// Pushes a refspec to a registered remote.
+    ///
+    /// ## Takes
+    /// - `refspec` - The refspec to push.
+    /// - `remote_name` - The name of the remote to push to.
+    ///
+    /// ## Returns
+    /// - `Result<()>` - The result of the operation.
+    fn push_refspec(&self, refspec: &str, remote_name: &str) -> Result<(), GitCommandError>;
+
+    /// Fetches a refspec from a registered remote.
+    ///
+    /// ## Takes
+    /// - `refspec` - The refspec to fetch.
+    /// - `remote_name` - The name of the remote to fetch from.
+    ///
+    /// ## Returns
+    /// - `Result<()>` - The result of the operation.
+    fn fetch_refspec(&self, refspec: &str, remote_name: &str) -> Result<(), GitCommandError>;
 }
 
 impl RepositoryExt for Repository {
-    fn current_branch(&self) -> Result<Branch, git2::Error> {
+    fn common_dir(&self) -> PathBuf {
+        // Linked worktrees record the path to the common directory in `$GIT_DIR/commondir`.
+        let git_dir = self.path();
+        match fs::read_to_string(git_dir.join("commondir")) {
+            Ok(common_dir) => {
+                let common_dir = git_dir.join(common_dir.trim());
+                fs::canonicalize(&common_dir).unwrap_or(common_dir)
+            }
+            Err(_) => git_dir.to_path_buf(),
+        }
+    }
+
+    fn worktree_branches(&self) -> Result<HashMap<String, PathBuf>, git2::Error> {
+        fn checked_out_branch(repository: &Repository) -> Option<(String, PathBuf)> {
+            let head = repository.head().ok()?;
+            if !head.is_branch() {
+                return None;
+            }
+            let branch = head.shorthand()?.to_string();
+            Some((branch, repository.workdir()?.to_path_buf()))
+        }
+
+        let mut branches = HashMap::new();
+
+        // The main worktree is not listed among the linked worktrees.
+        let main = if self.is_worktree() {
+            Some(Repository::open(self.common_dir())?)
+        } else {
+            None
+        };
+        branches.extend(checked_out_branch(main.as_ref().unwrap_or(self)));
+
+        for name in self.worktrees()?.iter().flatten() {
+            let worktree = self.find_worktree(name)?;
+            if worktree.validate().is_err() {
+                continue;
+            }
+            let repository = Repository::open_from_worktree(&worktree)?;
+            branches.extend(checked_out_branch(&repository));
+        }
+
+        Ok(branches)
+    }
+
+    fn checked_out_elsewhere(&self, branch_name: &str) -> Result<Option<PathBuf>, git2::Error> {
+        let canonical = |p: &Path| fs::canonicalize(p).unwrap_or(p.to_path_buf());
+        let current = self.workdir().map(canonical);
+
+        Ok(self
+            .worktree_branches()?
+            .remove(branch_name)
+            .filter(|path| Some(canonical(path)) != current))
+    }
+
+    fn current_branch(&self) -> Result<Branch<'_>, git2::Error> {
         let head = self.head()?;
         let branch = self.find_branch(
             head.name()
@@ -143,6 +307,55 @@ impl RepositoryExt for Repository {
         Ok(statuses.is_empty())
     }
 
+    fn branch_commits(
+        &self,
+        branch_name: &str,
+        parent_name: &str,
+    ) -> Result<Vec<Commit<'_>>, git2::Error> {
+        let branch = self.find_branch(branch_name, BranchType::Local)?;
+        let parent = self.find_branch(parent_name, BranchType::Local)?;
+
+        let mut revwalk = self.revwalk()?;
+        revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+        revwalk.push(branch.get().peel_to_commit()?.id())?;
+        revwalk.hide(parent.get().peel_to_commit()?.id())?;
+        revwalk
+            .map(|oid| oid.and_then(|oid| self.find_commit(oid)))
+            .collect()
+    }
+
+    fn changed_files(
+        &self,
+        branch_name: &str,
+        parent_name: &str,
+    ) -> Result<Vec<String>, git2::Error> {
+        let branch = self
+            .find_branch(branch_name, BranchType::Local)?
+            .get()
+            .peel_to_commit()?;
+        let parent = self
+            .find_branch(parent_name, BranchType::Local)?
+            .get()
+            .peel_to_commit()?;
+        let merge_base = self.find_commit(self.merge_base(branch.id(), parent.id())?)?;
+
+        let diff =
+            self.diff_tree_to_tree(Some(&merge_base.tree()?), Some(&branch.tree()?), None)?;
+        let mut paths = Vec::new();
+        for delta in diff.deltas() {
+            for file in [delta.old_file(), delta.new_file()] {
+                let Some(path) = file.path().and_then(Path::to_str) else {
+                    continue;
+                };
+                let path = path.to_string();
+                if !paths.contains(&path) {
+                    paths.push(path);
+                }
+            }
+        }
+        Ok(paths)
+    }
+
     fn checkout_branch(&self, branch_name: &str) -> Result<(), git2::Error> {
         if !self.is_working_tree_clean()? {
             return Err(git2::Error::new(
@@ -157,6 +370,24 @@ impl RepositoryExt for Repository {
         Ok(())
     }
 
+    fn checkout_detached(&self, branch_name: &str) -> Result<(), git2::Error> {
+        if !self.is_working_tree_clean()? {
+            return Err(git2::Error::new(
+                ErrorCode::GenericError,
+                ErrorClass::Checkout,
+                "Working tree is not clean. Commit or stash changes before checking out another branch.",
+            ));
+        }
+
+        let commit = self
+            .find_branch(branch_name, BranchType::Local)?
+            .get()
+            .peel_to_commit()?;
+        self.set_head_detached(commit.id())?;
+        self.checkout_head(Some(CheckoutBuilder::new().force()))?;
+        Ok(())
+    }
+
     fn set_target_to_upstream_ref(
         &self,
         branch_name: &str,
@@ -188,11 +419,25 @@ impl RepositoryExt for Repository {
 
         // Cheat and shell out to git to rebase the branch. This is mainly because git2 doesn't
         // create rebase-todo files, etc., and it's easier to just shell out to git.
-        execute_git_command(&["rebase", onto_name], false)
+        execute_git_command(self.workdir(), &["rebase", onto_name], false)
+    }
+
+    fn rebase_branch_range_onto(
+        &self,
+        branch_name: &str,
+        onto_name: &str,
+        upstream: &str,
+    ) -> Result<(), GitCommandError> {
+        self.checkout_branch(branch_name)?;
+        execute_git_command(
+            self.workdir(),
+            &["rebase", "--onto", onto_name, upstream, branch_name],
+            false,
+        )
     }
 
     fn abort_rebase(&self) -> Result<(), GitCommandError> {
-        execute_git_command(&["rebase", "--abort"], false)
+        execute_git_command(self.workdir(), &["rebase", "--abort"], false)
     }
 
     fn push_branch(
@@ -206,12 +451,20 @@ impl RepositoryExt for Repository {
             args.push("--force");
         }
 
-        execute_git_command(args.as_slice(), false)
+        execute_git_command(self.workdir(), args.as_slice(), false)
     }
 
     fn pull_branch(&self, branch_name: &str, remote_name: &str) -> Result<(), GitCommandError> {
         self.checkout_branch(branch_name)?;
-        execute_git_command(&["pull", remote_name, branch_name], false)
+        execute_git_command(self.workdir(), &["pull", remote_name, branch_name], false)
+    }
+
+    fn push_refspec(&self, refspec: &str, remote_name: &str) -> Result<(), GitCommandError> {
+        execute_git_command(self.workdir(), &["push", remote_name, refspec], false)
+    }
+
+    fn fetch_refspec(&self, refspec: &str, remote_name: &str) -> Result<(), GitCommandError> {
+        execute_git_command(self.workdir(), &["fetch", remote_name, refspec], false)
     }
 }
 
@@ -232,10 +485,18 @@ pub enum GitCommandError {
 /// Executes a `git` command with the given arguments in a blocking child task.
 ///
 /// ## Takes
+/// - `dir` - The directory to run the command in. Defaults to the current working directory.
 /// - `args` - The arguments to pass to the `git` command.
 /// - `interactive` - Whether the command should be interactive.
-fn execute_git_command(args: &[&str], interactive: bool) -> Result<(), GitCommandError> {
+fn execute_git_command(
+    dir: Option<&Path>,
+    args: &[&str],
+    interactive: bool,
+) -> Result<(), GitCommandError> {
     let mut cmd = Command::new("git");
+    if let Some(dir) = dir {
+        cmd.current_dir(dir);
+    }
     if interactive {
         let status = cmd.args(args).status()?;
 
@@ -0,0 +1,102 @@
+//! Fixtures shared by the tests: git repositories driven through the `git` CLI, and payloads of the
+//! GitHub API for the [MockGitHub](super::mock::MockGitHub) to answer with.
+
+use serde_json::{json, Value};
+use std::{path::Path, process::Command};
+
+/// Runs a git command in `dir`, returning its trimmed output.
+pub fn git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .args(["-c", "user.name=st", "-c", "user.email=st@example.com"])
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "git {:?}: {:?}", args, output);
+    String::from_utf8(output.stdout).unwrap().trim().to_string()
+}
+
+/// An open pull request from `head` into `main`, as returned by the REST API.
+pub fn pull(number: u64, head: &str) -> Value {
+    let url = format!("https://github.com/owner/repo/pull/{}", number);
+    json!({
+        "url": url,
+        "id": number,
+        "node_id": format!("PR_{}", number),
+        "html_url": url,
+        "number": number,
+        "state": "open",
+        "head": { "ref": head, "sha": "abc" },
+        "base": { "ref": "main", "sha": "def" },
+    })
+}
+
+/// The status of an open pull request as returned by the GraphQL API, aliased as `pr{number}`.
+/// `checks` holds the state of the check rollup and the conclusion of its only check, `test`.
+pub fn pull_status(number: u64, head_sha: &str, checks: Option<(&str, Value)>) -> Value {
+    let rollup = checks.map(|(state, conclusion)| {
+        json!({
+            "state": state,
+            "contexts": { "nodes": [{ "name": "test", "conclusion": conclusion }] },
+        })
+    });
+    json!({ "data": { "repository": { format!("pr{}", number): {
+        "number": number,
+        "title": format!("Pull request {}", number),
+        "body": "",
+        "state": "OPEN",
+        "isDraft": false,
+        "merged": false,
+        "baseRefName": "main",
+        "headRefName": format!("feat-{}", number),
+        "headRefOid": head_sha,
+        "reviewDecision": null,
+        "mergeable": "MERGEABLE",
+        "mergeStateStatus": "CLEAN",
+        "reviewThreads": { "nodes": [] },
+        "commits": { "nodes": [{ "commit": { "statusCheckRollup": rollup } }] },
+    } } } })
+}
+
+/// A user, as returned by the REST API.
+pub fn user(login: &str) -> Value {
+    let url = format!("https://api.github.com/users/{}", login);
+    json!({
+        "login": login,
+        "id": 1,
+        "node_id": "U_1",
+        "avatar_url": url,
+        "gravatar_id": "",
+        "url": url,
+        "html_url": url,
+        "followers_url": url,
+        "following_url": url,
+        "gists_url": url,
+        "starred_url": url,
+        "subscriptions_url": url,
+        "organizations_url": url,
+        "repos_url": url,
+        "events_url": url,
+        "received_events_url": url,
+        "type": "User",
+        "site_admin": false,
+    })
+}
+
+/// An issue comment, as returned by the REST API.
+pub fn comment(id: u64, login: &str, body: &str) -> Value {
+    let url = format!(
+        "https://api.github.com/repos/owner/repo/issues/comments/{}",
+        id
+    );
+    json!({
+        "id": id,
+        "node_id": format!("IC_{}", id),
+        "url": url,
+        "html_url": url,
+        "body": body,
+        "author_association": "OWNER",
+        "user": user(login),
+        "created_at": "2024-01-01T00:00:00Z",
+    })
+}
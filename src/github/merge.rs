@@ -0,0 +1,295 @@
+//! Merging of pull requests, and the waits between the steps of `merge`.
+
+use super::{
+    status::{CheckStatus, PullRequestStatus},
+    GitHubClient,
+};
+use crate::{
+    config::MergeMethod,
+    errors::{StError, StResult},
+};
+use octocrab::params;
+use std::time::{Duration, Instant};
+
+impl GitHubClient {
+    /// Merges a pull request into its base branch.
+    ///
+    /// ## Takes
+    /// - `number` - The number of the pull request.
+    /// - `method` - How to merge the pull request.
+    /// - `head_sha` - The commit that the head of the pull request must point to, so that changes
+    ///   pushed in the meantime are never merged unseen.
+    ///
+    /// ## Returns
+    /// - `Result<()>` - Nothing, or an error if GitHub refused to merge the pull request.
+    pub async fn merge_pull_request(
+        &self,
+        number: u64,
+        method: MergeMethod,
+        head_sha: &str,
+    ) -> StResult<()> {
+        let method = match method {
+            MergeMethod::Merge => params::pulls::MergeMethod::Merge,
+            MergeMethod::Squash => params::pulls::MergeMethod::Squash,
+            MergeMethod::Rebase => params::pulls::MergeMethod::Rebase,
+        };
+        let merge = self
+            .pulls()
+            .merge(number)
+            .method(method)
+            .sha(head_sha)
+            .send()
+            .await?;
+        if !merge.merged {
+            return Err(StError::MergeFailed(
+                number,
+                merge.message.unwrap_or_default(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Waits until GitHub reports a pull request as merged.
+    ///
+    /// ## Takes
+    /// - `number` - The number of the pull request.
+    /// - `interval` - The time between two polls.
+    /// - `timeout` - The time after which to give up.
+    ///
+    /// ## Returns
+    /// - `Result<()>` - Nothing once the pull request is merged, or an error on timeout.
+    pub async fn wait_for_merge(
+        &self,
+        number: u64,
+        interval: Duration,
+        timeout: Duration,
+    ) -> StResult<()> {
+        let start = Instant::now();
+        loop {
+            if self.pulls().get(number).await?.merged_at.is_some() {
+                return Ok(());
+            }
+            if start.elapsed() >= timeout {
+                return Err(StError::MergeTimedOut(number));
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    /// Waits until the checks of a pull request have settled.
+    ///
+    /// ## Takes
+    /// - `number` - The number of the pull request.
+    /// - `head_sha` - The commit that was just pushed to the head of the pull request. The status
+    ///   of any other commit is stale, and treated as pending.
+    /// - `interval` - The time between two polls.
+    /// - `grace` - The time during which a pull request without checks is treated as pending, as
+    ///   GitHub may not have queued the checks of the pushed commit yet.
+    /// - `timeout` - The time after which to give up.
+    ///
+    /// ## Returns
+    /// - `Result<PullRequestStatus>` - The status of the pull request once no check is pending, or
+    ///   an error if a check failed or the checks did not settle in time.
+    pub async fn wait_for_checks(
+        &self,
+        number: u64,
+        head_sha: &str,
+        interval: Duration,
+        grace: Duration,
+        timeout: Duration,
+    ) -> StResult<PullRequestStatus> {
+        let start = Instant::now();
+        loop {
+            let status = self
+                .pull_request_statuses(&[number])
+                .await?
+                .pop()
+                .ok_or(StError::PullRequestNotFound)?;
+            let checks = match status.checks {
+                _ if status.head_oid != head_sha => CheckStatus::Pending,
+                CheckStatus::None if start.elapsed() < grace => CheckStatus::Pending,
+                checks => checks,
+            };
+            match checks {
+                CheckStatus::Failing => {
+                    return Err(StError::ChecksFailed(
+                        number,
+                        status.failing_checks.join(", "),
+                    ))
+                }
+                CheckStatus::Pending if start.elapsed() < timeout => {
+                    tokio::time::sleep(interval).await
+                }
+                CheckStatus::Pending => return Err(StError::ChecksTimedOut(number)),
+                CheckStatus::Passing | CheckStatus::None => return Ok(status),
+            }
+        }
+    }
+
+    /// Changes the base branch of a pull request.
+    pub async fn retarget_pull_request(&self, number: u64, base: &str) -> StResult<()> {
+        self.pulls().update(number).base(base).send().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        config::MergeMethod,
+        errors::StError,
+        github::{
+            fixtures::{pull, pull_status},
+            mock::MockGitHub,
+            status::CheckStatus,
+        },
+    };
+    use serde_json::{json, Value};
+    use std::time::Duration;
+
+    /// A pull request, merged at `merged_at`, as returned by the REST API.
+    fn merged_pull(number: u64, merged_at: Option<&str>) -> Value {
+        let mut pr = pull(number, &format!("feat-{}", number));
+        pr["merged_at"] = json!(merged_at);
+        pr
+    }
+
+    #[tokio::test]
+    async fn merges_and_retargets_against_mock_api() {
+        let mut polls = 0;
+        let mock = MockGitHub::start(move |request| {
+            match (request.method.as_str(), request.path.as_str()) {
+                ("PUT", "/repos/owner/repo/pulls/1/merge") => (
+                    200,
+                    json!({ "sha": "123", "merged": true, "message": "Merged" }),
+                ),
+                ("GET", "/repos/owner/repo/pulls/1") => {
+                    polls += 1;
+                    (
+                        200,
+                        merged_pull(1, (polls > 1).then_some("2024-01-01T00:00:00Z")),
+                    )
+                }
+                ("PATCH", "/repos/owner/repo/pulls/2") => (200, pull(2, "feat-2")),
+                _ => (404, json!({ "message": "Not Found" })),
+            }
+        })
+        .await;
+        let client = mock.client();
+
+        client
+            .merge_pull_request(1, MergeMethod::Squash, "abc")
+            .await
+            .unwrap();
+        client
+            .wait_for_merge(1, Duration::ZERO, Duration::from_secs(5))
+            .await
+            .unwrap();
+        client.retarget_pull_request(2, "main").await.unwrap();
+
+        let requests = mock.requests();
+        let routes = requests
+            .iter()
+            .map(|r| format!("{} {}", r.method, r.path))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            routes,
+            [
+                "PUT /repos/owner/repo/pulls/1/merge",
+                "GET /repos/owner/repo/pulls/1",
+                "GET /repos/owner/repo/pulls/1",
+                "PATCH /repos/owner/repo/pulls/2",
+            ]
+        );
+        assert_eq!(
+            requests[0].body,
+            json!({ "sha": "abc", "merge_method": "squash" })
+        );
+        assert_eq!(requests[3].body["base"], "main");
+    }
+
+    #[tokio::test]
+    async fn waits_for_checks_against_mock_api() {
+        // Checks are pending on the first poll, and one of them fails on the second.
+        let mut polls = 0;
+        let mock = MockGitHub::start(move |_| {
+            polls += 1;
+            match polls {
+                1 => (200, pull_status(2, "abc", Some(("PENDING", Value::Null)))),
+                _ => (
+                    200,
+                    pull_status(2, "abc", Some(("FAILURE", json!("FAILURE")))),
+                ),
+            }
+        })
+        .await;
+
+        let result = mock
+            .client()
+            .wait_for_checks(
+                2,
+                "abc",
+                Duration::ZERO,
+                Duration::ZERO,
+                Duration::from_secs(5),
+            )
+            .await;
+        assert!(matches!(result, Err(StError::ChecksFailed(2, ref names)) if names == "test"));
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests.iter().all(|r| r.path == "/graphql"));
+    }
+
+    #[tokio::test]
+    async fn waits_for_checks_of_pushed_head() {
+        // GitHub reports the failed checks of the previous head until it sees the push, then no
+        // checks until they are queued, then pending and passing checks.
+        let mut polls = 0;
+        let mock = MockGitHub::start(move |_| {
+            polls += 1;
+            match polls {
+                1 => (
+                    200,
+                    pull_status(2, "old", Some(("FAILURE", json!("FAILURE")))),
+                ),
+                2 => (200, pull_status(2, "new", None)),
+                3 => (200, pull_status(2, "new", Some(("PENDING", Value::Null)))),
+                _ => (
+                    200,
+                    pull_status(2, "new", Some(("SUCCESS", json!("SUCCESS")))),
+                ),
+            }
+        })
+        .await;
+
+        let status = mock
+            .client()
+            .wait_for_checks(
+                2,
+                "new",
+                Duration::ZERO,
+                Duration::from_secs(60),
+                Duration::from_secs(60),
+            )
+            .await
+            .unwrap();
+        assert_eq!(status.head_oid, "new");
+        assert_eq!(status.checks, CheckStatus::Passing);
+        assert_eq!(mock.requests().len(), 4);
+
+        // Without a grace period, a pull request without checks has settled.
+        let mock = MockGitHub::start(|_| (200, pull_status(2, "new", None))).await;
+        let status = mock
+            .client()
+            .wait_for_checks(
+                2,
+                "new",
+                Duration::ZERO,
+                Duration::ZERO,
+                Duration::from_secs(60),
+            )
+            .await
+            .unwrap();
+        assert_eq!(status.checks, CheckStatus::None);
+    }
+}
@@ -0,0 +1,129 @@
+//! A local mock of the GitHub API, for testing the requests that `st` sends.
+
+use super::{GitHubClient, GitHubHost};
+use serde_json::Value;
+use std::sync::{Arc, Mutex};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+};
+
+/// A request received by the [MockGitHub].
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+    /// The HTTP method, e.g. `GET`.
+    pub method: String,
+    /// The path of the request, without its query string.
+    pub path: String,
+    /// The query string of the request, without the leading `?`.
+    pub query: String,
+    /// The JSON body of the request, or [Value::Null].
+    pub body: Value,
+}
+
+/// Answers a request with a status code and a JSON body.
+type Handler = dyn FnMut(&MockRequest) -> (u16, Value) + Send;
+
+/// A local HTTP server standing in for the REST and GraphQL APIs of GitHub.
+pub struct MockGitHub {
+    /// The base URL of the server.
+    url: String,
+    /// The requests received so far, in order.
+    requests: Arc<Mutex<Vec<MockRequest>>>,
+}
+
+impl MockGitHub {
+    /// Starts a server that answers every request with `handler`. GraphQL requests are sent to
+    /// `POST /graphql`.
+    pub async fn start(handler: impl FnMut(&MockRequest) -> (u16, Value) + Send + 'static) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let handler: Arc<Mutex<Box<Handler>>> = Arc::new(Mutex::new(Box::new(handler)));
+
+        let received = Arc::clone(&requests);
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let (received, handler) = (Arc::clone(&received), Arc::clone(&handler));
+                tokio::spawn(serve(stream, received, handler));
+            }
+        });
+        Self { url, requests }
+    }
+
+    /// Returns the base URL of the server.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Returns a client for the `owner/repo` repository, talking to the server.
+    pub fn client(&self) -> GitHubClient {
+        let host = GitHubHost {
+            api_url: self.url.clone(),
+            web_url: self.url.clone(),
+        };
+        GitHubClient::new(&host, "token", "owner".to_string(), "repo".to_string()).unwrap()
+    }
+
+    /// Returns the requests received so far, in order.
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+/// Serves the requests of a connection until the client closes it.
+async fn serve(
+    stream: TcpStream,
+    received: Arc<Mutex<Vec<MockRequest>>>,
+    handler: Arc<Mutex<Box<Handler>>>,
+) {
+    let mut stream = BufReader::new(stream);
+    loop {
+        let mut line = String::new();
+        if stream.read_line(&mut line).await.unwrap_or_default() == 0 {
+            return;
+        }
+        let mut parts = line.split_whitespace();
+        let method = parts.next().unwrap_or_default().to_string();
+        let target = parts.next().unwrap_or_default();
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let (path, query) = (path.to_string(), query.to_string());
+
+        // Read the headers, keeping the length of the body.
+        let mut length = 0;
+        loop {
+            let mut header = String::new();
+            stream.read_line(&mut header).await.unwrap();
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    length = value.trim().parse().unwrap();
+                }
+            }
+        }
+        let mut body = vec![0; length];
+        stream.read_exact(&mut body).await.unwrap();
+
+        let request = MockRequest {
+            method,
+            path,
+            query,
+            body: serde_json::from_slice(&body).unwrap_or_default(),
+        };
+        let (status, response) = (handler.lock().unwrap())(&request);
+        received.lock().unwrap().push(request);
+
+        let response = response.to_string();
+        let head = format!(
+            "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n",
+            status,
+            response.len()
+        );
+        let stream = stream.get_mut();
+        stream.write_all(head.as_bytes()).await.unwrap();
+        stream.write_all(response.as_bytes()).await.unwrap();
+    }
+}
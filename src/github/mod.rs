@@ -0,0 +1,524 @@
+//! Resolution of the GitHub instance that hosts a repository, supporting both `github.com` and
+//! GitHub Enterprise Server.
+
+use crate::{
+    config::StConfig,
+    constants::GITHUB_MAX_CONCURRENT_REQUESTS,
+    errors::{register_secret, RedactedOctocrabError, StError, StResult},
+};
+use nu_ansi_term::Color;
+use octocrab::{
+    issues::IssueHandler, models::pulls::PullRequest, pulls::PullRequestHandler, Octocrab,
+};
+use serde_json::Value;
+use std::{future::Future, sync::Arc};
+use tokio::{sync::Semaphore, task::JoinSet};
+
+pub mod status;
+pub use status::{PullRequestState, PullRequestStatus};
+
+mod merge;
+
+#[cfg(test)]
+pub mod fixtures;
+#[cfg(test)]
+pub mod mock;
+
+/// The host name of the public GitHub instance.
+pub const GITHUB_HOST: &str = "github.com";
+
+/// The API and web endpoints of a GitHub instance.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct GitHubHost {
+    /// The base URL of the REST API, e.g. `https://api.github.com`.
+    pub api_url: String,
+    /// The base URL of the web interface, e.g. `https://github.com`.
+    pub web_url: String,
+}
+
+impl GitHubHost {
+    /// Resolves the endpoints of the GitHub instance with the given host name.
+    ///
+    /// Endpoints configured in the `hosts` table of the [StConfig] take precedence. Otherwise,
+    /// `github.com` uses the public API, and any other host is assumed to be a GitHub Enterprise
+    /// Server instance serving its API under `/api/v3`.
+    ///
+    /// ## Takes
+    /// - `host` - The host name of the remote, e.g. `github.example.com`.
+    /// - `cfg` - The global configuration.
+    ///
+    /// ## Returns
+    /// - `GitHubHost` - The endpoints of the instance.
+    pub fn resolve(host: &str, cfg: &StConfig) -> Self {
+        let (api_url, web_url) = if host == GITHUB_HOST {
+            (
+                "https://api.github.com".to_string(),
+                "https://github.com".to_string(),
+            )
+        } else {
+            (
+                format!("https://{}/api/v3", host),
+                format!("https://{}", host),
+            )
+        };
+
+        let overrides = cfg.hosts.get(host);
+        let api_url = overrides.and_then(|h| h.api_url.clone()).unwrap_or(api_url);
+        let web_url = overrides.and_then(|h| h.web_url.clone()).unwrap_or(web_url);
+
+        Self {
+            api_url: api_url.trim_end_matches('/').to_string(),
+            web_url: web_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Builds an API client for the instance, authenticated with the given token.
+    pub fn client(&self, token: &str) -> StResult<Octocrab> {
+        register_secret(token);
+        Ok(Octocrab::builder()
+            .base_uri(self.api_url.as_str())?
+            .personal_token(token.to_string())
+            .build()?)
+    }
+
+    /// Builds a GraphQL API client for the instance, authenticated with the given token.
+    ///
+    /// The GraphQL API of GitHub Enterprise Server is served at `/api/graphql` rather than under
+    /// the `/api/v3` prefix of the REST API.
+    pub fn graphql_client(&self, token: &str) -> StResult<Octocrab> {
+        let base = self.api_url.strip_suffix("/v3").unwrap_or(&self.api_url);
+        register_secret(token);
+        Ok(Octocrab::builder()
+            .base_uri(base)?
+            .personal_token(token.to_string())
+            .build()?)
+    }
+
+    /// Returns the web URL of a pull request.
+    pub fn pull_request_url(&self, owner: &str, repo: &str, number: u64) -> String {
+        format!("{}/{}/{}/pull/{}", self.web_url, owner, repo, number)
+    }
+
+    /// Returns the web URL comparing the `head` branch against the `base` branch.
+    pub fn compare_url(&self, owner: &str, repo: &str, base: &str, head: &str) -> String {
+        format!(
+            "{}/{}/{}/compare/{}...{}",
+            self.web_url, owner, repo, base, head
+        )
+    }
+}
+
+/// A client for the GitHub repository that pull requests are opened against, shared by every
+/// command that talks to the GitHub API.
+#[derive(Debug, Clone)]
+pub struct GitHubClient {
+    /// The REST API client.
+    rest: Octocrab,
+    /// The GraphQL API client.
+    graphql: Octocrab,
+    /// The owner of the repository.
+    pub owner: String,
+    /// The name of the repository.
+    pub repo: String,
+}
+
+impl GitHubClient {
+    /// Builds a client for a repository of the given GitHub instance.
+    ///
+    /// ## Takes
+    /// - `host` - The endpoints of the GitHub instance.
+    /// - `token` - The token to authenticate with.
+    /// - `owner` - The owner of the repository.
+    /// - `repo` - The name of the repository.
+    ///
+    /// ## Returns
+    /// - `Result<GitHubClient>` - The client, or an error.
+    pub fn new(host: &GitHubHost, token: &str, owner: String, repo: String) -> StResult<Self> {
+        Ok(Self {
+            rest: host.client(token)?,
+            graphql: host.graphql_client(token)?,
+            owner,
+            repo,
+        })
+    }
+
+    /// Returns the underlying REST API client, for requests without a dedicated handler.
+    pub fn octocrab(&self) -> &Octocrab {
+        &self.rest
+    }
+
+    /// Returns the pull request handler of the repository.
+    pub fn pulls(&self) -> PullRequestHandler<'_> {
+        self.rest.pulls(&self.owner, &self.repo)
+    }
+
+    /// Returns the issue handler of the repository.
+    pub fn issues(&self) -> IssueHandler<'_> {
+        self.rest.issues(&self.owner, &self.repo)
+    }
+
+    /// Returns the login of the user that the client is authenticated as.
+    pub async fn viewer_login(&self) -> StResult<String> {
+        Ok(self.rest.current().user().await?.login)
+    }
+
+    /// Executes a GraphQL query or mutation. See [graphql].
+    pub async fn graphql(&self, query: &str, variables: Value) -> StResult<Value> {
+        graphql(&self.graphql, query, variables).await
+    }
+
+    /// Fetches pull requests concurrently.
+    ///
+    /// ## Takes
+    /// - `numbers` - The numbers of the pull requests.
+    ///
+    /// ## Returns
+    /// - `Result<Vec<PullRequest>>` - The pull requests, in the order of `numbers`.
+    pub async fn pull_requests(&self, numbers: &[u64]) -> StResult<Vec<PullRequest>> {
+        concurrently(numbers.iter().copied(), |number| {
+            let client = self.clone();
+            async move { Ok(client.pulls().get(number).await?) }
+        })
+        .await
+    }
+
+    /// Fetches the status of pull requests with a single GraphQL query, falling back to one REST
+    /// request per pull request if the GraphQL API is unavailable.
+    ///
+    /// ## Takes
+    /// - `numbers` - The numbers of the pull requests.
+    ///
+    /// ## Returns
+    /// - `Result<Vec<PullRequestStatus>>` - The statuses, in the order of `numbers`.
+    pub async fn pull_request_statuses(&self, numbers: &[u64]) -> StResult<Vec<PullRequestStatus>> {
+        if numbers.is_empty() {
+            return Ok(Vec::new());
+        }
+        match PullRequestStatus::query(self, numbers).await {
+            Err(e) if is_graphql_unavailable(&e) => {
+                eprintln!(
+                    "{} The GraphQL API is unavailable ({}). Falling back to the REST API.",
+                    Color::Yellow.paint("notice:"),
+                    e
+                );
+                concurrently(numbers.iter().copied(), |number| {
+                    let client = self.clone();
+                    async move { PullRequestStatus::fetch(&client, number).await }
+                })
+                .await
+            }
+            result => result,
+        }
+    }
+}
+
+/// Runs a task for each item concurrently, with at most [GITHUB_MAX_CONCURRENT_REQUESTS] tasks in
+/// flight at once.
+///
+/// ## Takes
+/// - `items` - The inputs of the tasks.
+/// - `task` - Builds the task of an item.
+///
+/// ## Returns
+/// - `Result<Vec<T>>` - The outputs of the tasks, in the order of `items`, or the first error.
+pub async fn concurrently<I, T, F, Fut>(
+    items: impl IntoIterator<Item = I>,
+    task: F,
+) -> StResult<Vec<T>>
+where
+    F: Fn(I) -> Fut,
+    Fut: Future<Output = StResult<T>> + Send + 'static,
+    T: Send + 'static,
+{
+    let permits = Arc::new(Semaphore::new(GITHUB_MAX_CONCURRENT_REQUESTS));
+    let mut tasks = JoinSet::new();
+    let mut len = 0;
+    for (i, item) in items.into_iter().enumerate() {
+        let permits = Arc::clone(&permits);
+        let future = task(item);
+        tasks.spawn(async move {
+            let _permit = permits
+                .acquire_owned()
+                .await
+                .expect("Semaphore is never closed");
+            (i, future.await)
+        });
+        len += 1;
+    }
+
+    let mut results = (0..len).map(|_| None).collect::<Vec<_>>();
+    while let Some(joined) = tasks.join_next().await {
+        let (i, result) = joined.expect("GitHub API tasks do not panic");
+        results[i] = Some(result?);
+    }
+    Ok(results.into_iter().flatten().collect())
+}
+
+/// Returns whether a GitHub API error is a `404 Not Found` response, e.g. for a deleted comment.
+pub fn is_not_found(error: &octocrab::Error) -> bool {
+    matches!(error, octocrab::Error::GitHub { source, .. } if source.status_code.as_u16() == 404)
+}
+
+/// Returns whether a GraphQL request failed because the API, or a field of the query, is not
+/// available on the GitHub instance. Other errors, e.g. of authentication or rate limits, would
+/// fail the REST API as well.
+fn is_graphql_unavailable(error: &StError) -> bool {
+    match error {
+        StError::OctocrabError(RedactedOctocrabError(octocrab::Error::GitHub {
+            source, ..
+        })) => {
+            matches!(source.status_code.as_u16(), 404 | 501)
+        }
+        StError::GraphQLSchemaError(_) => true,
+        _ => false,
+    }
+}
+
+/// Executes a GraphQL query or mutation.
+///
+/// ## Takes
+/// - `client` - A client built with [GitHubHost::graphql_client].
+/// - `query` - The GraphQL document.
+/// - `variables` - The variables of the document.
+///
+/// ## Returns
+/// - `Ok(Value)` - The `data` of the response.
+/// - `Err(StError::GraphQLSchemaError)` - If the query does not validate against the schema of
+///   the instance.
+/// - `Err(StError::GraphQLError)` - If the response holds other errors.
+pub async fn graphql(client: &Octocrab, query: &str, variables: Value) -> StResult<Value> {
+    let mut response: Value = client
+        .graphql(&serde_json::json!({ "query": query, "variables": variables }))
+        .await?;
+
+    if let Some(errors) = response.get("errors").and_then(Value::as_array) {
+        let messages = errors
+            .iter()
+            .map(|e| {
+                e.get("message")
+                    .and_then(Value::as_str)
+                    .unwrap_or("unknown error")
+            })
+            .collect::<Vec<_>>();
+        // Validation errors carry a code, while errors raised while executing the query carry a
+        // type, e.g. `RATE_LIMITED`.
+        let is_schema_error = errors
+            .iter()
+            .all(|e| e["extensions"].get("code").is_some() && e.get("type").is_none());
+        let messages = messages.join("; ");
+        return Err(if is_schema_error {
+            StError::GraphQLSchemaError(messages)
+        } else {
+            StError::GraphQLError(messages)
+        });
+    }
+    Ok(response
+        .get_mut("data")
+        .map(Value::take)
+        .unwrap_or_default())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        concurrently, fixtures::pull, graphql, mock::MockGitHub, GitHubHost, PullRequestState,
+    };
+    use crate::{
+        config::{HostConfig, StConfig},
+        constants::GITHUB_MAX_CONCURRENT_REQUESTS,
+        errors::StError,
+    };
+    use serde_json::json;
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    #[tokio::test]
+    async fn concurrent_tasks_are_bounded_and_ordered() {
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+
+        // Later items finish first, so completion order differs from input order.
+        let results = concurrently(0..20u64, |i| {
+            let (in_flight, peak) = (Arc::clone(&in_flight), Arc::clone(&peak));
+            async move {
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(20 - i)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                Ok(i * 2)
+            }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(results, (0..20).map(|i| i * 2).collect::<Vec<_>>());
+        assert!(peak.load(Ordering::SeqCst) <= GITHUB_MAX_CONCURRENT_REQUESTS);
+        assert!(peak.load(Ordering::SeqCst) > 1);
+    }
+
+    #[test]
+    fn resolves_public_github() {
+        let host = GitHubHost::resolve("github.com", &StConfig::default());
+        assert_eq!(host.api_url, "https://api.github.com");
+        assert_eq!(
+            host.pull_request_url("clabby", "st", 1),
+            "https://github.com/clabby/st/pull/1"
+        );
+    }
+
+    #[test]
+    fn detects_enterprise_server_endpoints() {
+        let host = GitHubHost::resolve("github.example.com", &StConfig::default());
+        assert_eq!(host.api_url, "https://github.example.com/api/v3");
+        assert_eq!(
+            host.compare_url("team", "app", "main", "feature"),
+            "https://github.example.com/team/app/compare/main...feature"
+        );
+    }
+
+    #[test]
+    fn prefers_configured_endpoints() {
+        let mut cfg = StConfig::default();
+        cfg.hosts.insert(
+            "git.corp".to_string(),
+            HostConfig {
+                api_url: Some("https://api.git.corp/".to_string()),
+                web_url: None,
+                token: None,
+            },
+        );
+
+        let host = GitHubHost::resolve("git.corp", &cfg);
+        assert_eq!(host.api_url, "https://api.git.corp");
+        assert_eq!(host.web_url, "https://git.corp");
+    }
+
+    #[tokio::test]
+    async fn client_uses_configured_api_url() {
+        let mock = MockGitHub::start(|_| (200, pull(7, "feature"))).await;
+
+        let mut cfg = StConfig::default();
+        cfg.hosts.insert(
+            "github.example.com".to_string(),
+            HostConfig {
+                api_url: Some(format!("{}/api/v3", mock.url())),
+                web_url: None,
+                token: None,
+            },
+        );
+        let host = GitHubHost::resolve("github.example.com", &cfg);
+        let pr = host
+            .client("token")
+            .unwrap()
+            .pulls("team", "app")
+            .get(7)
+            .await
+            .unwrap();
+
+        assert_eq!(pr.number, 7);
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, "GET");
+        assert_eq!(requests[0].path, "/api/v3/repos/team/app/pulls/7");
+    }
+
+    #[tokio::test]
+    async fn graphql_uses_enterprise_endpoint() {
+        let mock = MockGitHub::start(|_| {
+            (
+                200,
+                json!({ "data": null, "errors": [{ "message": "Pull request is not a draft" }] }),
+            )
+        })
+        .await;
+
+        let mut cfg = StConfig::default();
+        cfg.hosts.insert(
+            "github.example.com".to_string(),
+            HostConfig {
+                api_url: Some(format!("{}/api/v3", mock.url())),
+                web_url: None,
+                token: None,
+            },
+        );
+        let client = GitHubHost::resolve("github.example.com", &cfg)
+            .graphql_client("token")
+            .unwrap();
+        let result = graphql(&client, "mutation { noop }", json!({})).await;
+
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("Pull request is not a draft"));
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, "POST");
+        assert_eq!(requests[0].path, "/api/graphql");
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_rest_when_graphql_is_unavailable() {
+        let unavailable = [
+            (404, json!({ "message": "Not Found" })),
+            (
+                200,
+                json!({ "errors": [{
+                    "message": "Field 'mergeStateStatus' doesn't exist on type 'PullRequest'",
+                    "extensions": { "code": "undefinedField" },
+                }] }),
+            ),
+        ];
+        for (status, response) in unavailable {
+            let mock = MockGitHub::start(move |request| match request.path.as_str() {
+                "/graphql" => (status, response.clone()),
+                "/repos/owner/repo/pulls/1" => {
+                    let mut pr = pull(1, "a");
+                    pr["state"] = json!("closed");
+                    pr["merged_at"] = json!("2024-01-01T00:00:00Z");
+                    (200, pr)
+                }
+                "/repos/owner/repo/commits/abc/check-runs" => (200, json!({ "check_runs": [] })),
+                "/repos/owner/repo/commits/abc/status" => (200, json!({ "statuses": [] })),
+                _ => (404, json!({ "message": "Not Found" })),
+            })
+            .await;
+
+            let statuses = mock.client().pull_request_statuses(&[1]).await.unwrap();
+            assert_eq!(statuses.len(), 1);
+            assert_eq!(statuses[0].state, PullRequestState::Merged);
+            assert_eq!(statuses[0].head_ref, "a");
+            assert_eq!(mock.requests()[0].path, "/graphql");
+        }
+    }
+
+    #[tokio::test]
+    async fn propagates_graphql_auth_and_rate_limit_errors() {
+        let failures = [
+            (401, json!({ "message": "Bad credentials" })),
+            (
+                200,
+                json!({ "errors": [{
+                    "type": "RATE_LIMITED",
+                    "message": "API rate limit exceeded",
+                }] }),
+            ),
+        ];
+        for (status, response) in failures {
+            let mock = MockGitHub::start(move |_| (status, response.clone())).await;
+
+            let result = mock.client().pull_request_statuses(&[1]).await;
+            assert!(matches!(
+                result,
+                Err(StError::OctocrabError(_) | StError::GraphQLError(_))
+            ));
+            let requests = mock.requests();
+            assert_eq!(requests.len(), 1);
+            assert_eq!(requests[0].path, "/graphql");
+        }
+    }
+}
@@ -0,0 +1,586 @@
+//! Status of pull requests, queried for a whole stack at once through the GraphQL API, with a
+//! fallback to the REST API.
+
+use super::GitHubClient;
+use crate::errors::{StError, StResult};
+use octocrab::models::{
+    pulls::{MergeableState, PullRequest, ReviewState},
+    IssueState,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+};
+
+/// The maximum number of pull requests queried in a single GraphQL request.
+const GRAPHQL_BATCH_SIZE: usize = 50;
+
+/// The fields of each pull request queried through GraphQL.
+const PULL_REQUEST_FIELDS: &str = "number title body state isDraft merged baseRefName \
+    headRefName headRefOid reviewDecision mergeable mergeStateStatus \
+    reviewThreads(first: 100) { nodes { isResolved } } \
+    commits(last: 1) { nodes { commit { statusCheckRollup { state contexts(first: 100) { nodes { \
+    ... on CheckRun { name conclusion } ... on StatusContext { context state } } } } } } }";
+
+/// The state of a pull request.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PullRequestState {
+    /// The pull request is open, and marked as a draft.
+    Draft,
+    /// The pull request is open.
+    Open,
+    /// The pull request has been merged.
+    Merged,
+    /// The pull request has been closed without being merged.
+    Closed,
+}
+
+impl Display for PullRequestState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Draft => write!(f, "📝 Draft"),
+            Self::Open => write!(f, "🟢 Open"),
+            Self::Merged => write!(f, "🟣 Merged"),
+            Self::Closed => write!(f, "🔴 Closed"),
+        }
+    }
+}
+
+/// The review decision of a pull request.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReviewStatus {
+    /// A reviewer requested changes.
+    ChangesRequested,
+    /// The pull request was approved, and no reviewer requested changes.
+    Approved,
+    /// Reviews are required, but none approved or requested changes yet.
+    ReviewRequired,
+    /// No reviews are required.
+    None,
+}
+
+impl Display for ReviewStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ChangesRequested => write!(f, "❌ Changes requested"),
+            Self::Approved => write!(f, "✅ Approved"),
+            Self::ReviewRequired => write!(f, "👀 Review required"),
+            Self::None => write!(f, "No reviews"),
+        }
+    }
+}
+
+/// Whether a pull request can be merged without conflicts.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Mergeability {
+    /// The pull request can be merged cleanly.
+    Mergeable,
+    /// The pull request can be merged, but its head is behind its base branch.
+    Behind,
+    /// The pull request conflicts with its base branch.
+    Conflicting,
+    /// GitHub has not computed the mergeability yet.
+    #[default]
+    Unknown,
+}
+
+impl Display for Mergeability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Mergeable => write!(f, "✅ Clean"),
+            Self::Behind => write!(f, "⏪ Behind base"),
+            Self::Conflicting => write!(f, "⚠️ Conflicts"),
+            Self::Unknown => write!(f, "❔ Unknown"),
+        }
+    }
+}
+
+/// The combined status of the checks and commit statuses of a pull request's head commit.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CheckStatus {
+    /// Every check passed.
+    Passing,
+    /// At least one check failed.
+    Failing,
+    /// No check failed, but some are still running.
+    Pending,
+    /// The head commit has no checks.
+    None,
+}
+
+impl Display for CheckStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Passing => write!(f, "✅ Checks passing"),
+            Self::Failing => write!(f, "❌ Checks failing"),
+            Self::Pending => write!(f, "⏳ Checks pending"),
+            Self::None => write!(f, "No checks"),
+        }
+    }
+}
+
+impl CheckStatus {
+    /// Combines the statuses of individual checks. Failures take precedence over pending checks.
+    pub fn rollup(statuses: impl IntoIterator<Item = Self>) -> Self {
+        statuses
+            .into_iter()
+            .fold(Self::None, |rollup, status| match (rollup, status) {
+                (Self::Failing, _) | (_, Self::Failing) => Self::Failing,
+                (Self::Pending, _) | (_, Self::Pending) => Self::Pending,
+                (Self::Passing, _) | (_, Self::Passing) => Self::Passing,
+                _ => Self::None,
+            })
+    }
+}
+
+/// The status of a pull request, as shown by `status`, `log` and the stack overviews.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PullRequestStatus {
+    /// The number of the pull request.
+    pub number: u64,
+    /// The title of the pull request.
+    pub title: String,
+    /// The description of the pull request.
+    pub body: String,
+    /// The state of the pull request.
+    pub state: PullRequestState,
+    /// The name of the base branch.
+    pub base_ref: String,
+    /// The name of the head branch.
+    pub head_ref: String,
+    /// The object ID of the head commit.
+    pub head_oid: String,
+    /// The review decision of the pull request.
+    pub review: ReviewStatus,
+    /// Whether the pull request can be merged without conflicts.
+    pub mergeable: Mergeability,
+    /// The combined status of the checks of the head commit.
+    pub checks: CheckStatus,
+    /// The names of the failing checks of the head commit.
+    pub failing_checks: Vec<String>,
+    /// The number of unresolved review conversations, if known. The REST API does not expose
+    /// whether conversations are resolved.
+    pub unresolved_threads: Option<u64>,
+}
+
+/// A pull request, as returned by the GraphQL API.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphQLPullRequest {
+    number: u64,
+    title: String,
+    body: String,
+    state: String,
+    is_draft: bool,
+    merged: bool,
+    base_ref_name: String,
+    head_ref_name: String,
+    head_ref_oid: String,
+    review_decision: Option<String>,
+    mergeable: String,
+    merge_state_status: String,
+    review_threads: GraphQLNodes<GraphQLReviewThread>,
+    commits: GraphQLNodes<GraphQLCommitNode>,
+}
+
+/// A review conversation, as returned by the GraphQL API.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphQLReviewThread {
+    is_resolved: bool,
+}
+
+/// A GraphQL connection.
+#[derive(Debug, Deserialize)]
+struct GraphQLNodes<T> {
+    nodes: Vec<T>,
+}
+
+/// A commit of a pull request, as returned by the GraphQL API.
+#[derive(Debug, Deserialize)]
+struct GraphQLCommitNode {
+    commit: GraphQLCommit,
+}
+
+/// The check rollup of a commit, as returned by the GraphQL API.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphQLCommit {
+    status_check_rollup: Option<GraphQLRollup>,
+}
+
+/// A check rollup, as returned by the GraphQL API.
+#[derive(Debug, Deserialize)]
+struct GraphQLRollup {
+    state: String,
+    contexts: GraphQLNodes<GraphQLCheckContext>,
+}
+
+/// A check run or commit status within a check rollup, as returned by the GraphQL API.
+#[derive(Debug, Deserialize)]
+struct GraphQLCheckContext {
+    /// The name of a check run.
+    name: Option<String>,
+    /// The conclusion of a check run, unset while it runs.
+    conclusion: Option<String>,
+    /// The name of a commit status.
+    context: Option<String>,
+    /// The state of a commit status.
+    state: Option<String>,
+}
+
+impl PullRequestStatus {
+    /// Queries the status of pull requests through the GraphQL API, in as few requests as
+    /// possible.
+    ///
+    /// ## Takes
+    /// - `client` - The client of the repository.
+    /// - `numbers` - The numbers of the pull requests.
+    ///
+    /// ## Returns
+    /// - `Result<Vec<PullRequestStatus>>` - The statuses, in the order of `numbers`.
+    pub async fn query(client: &GitHubClient, numbers: &[u64]) -> StResult<Vec<Self>> {
+        let mut statuses = Vec::with_capacity(numbers.len());
+        for batch in numbers.chunks(GRAPHQL_BATCH_SIZE) {
+            let data = client
+                .graphql(
+                    &Self::graphql_query(batch),
+                    serde_json::json!({ "owner": client.owner, "repo": client.repo }),
+                )
+                .await?;
+            statuses.extend(Self::from_graphql(&data, batch)?);
+        }
+        Ok(statuses)
+    }
+
+    /// Fetches the status of a pull request through the REST API.
+    ///
+    /// ## Takes
+    /// - `client` - The client of the repository.
+    /// - `number` - The number of the pull request.
+    ///
+    /// ## Returns
+    /// - `Result<PullRequestStatus>` - The status, or an error.
+    pub async fn fetch(client: &GitHubClient, number: u64) -> StResult<Self> {
+        let pr = client.pulls().get(number).await?;
+        let state = Self::state_of(&pr);
+        let is_open = matches!(state, PullRequestState::Open | PullRequestState::Draft);
+
+        let review = if is_open {
+            let reviews = client
+                .pulls()
+                .list_reviews(number)
+                .per_page(100)
+                .send()
+                .await?;
+            let latest = reviews
+                .items
+                .into_iter()
+                .filter_map(|r| Some((r.user?.login, r.state?)))
+                .filter(|(_, s)| !matches!(s, ReviewState::Commented | ReviewState::Pending))
+                .collect::<HashMap<_, _>>();
+            let requested = pr.requested_reviewers.iter().flatten().next().is_some()
+                || pr.requested_teams.iter().flatten().next().is_some();
+            Self::review_of(latest.values(), requested)
+        } else {
+            ReviewStatus::None
+        };
+
+        // Combine the check runs and the commit statuses of the head commit.
+        let head_oid = pr.head.sha.clone();
+        let route = |path: &str| {
+            format!(
+                "/repos/{}/{}/commits/{}/{}",
+                client.owner, client.repo, head_oid, path
+            )
+        };
+        let check_runs: Value = client
+            .octocrab()
+            .get(route("check-runs"), Some(&[("per_page", "100")]))
+            .await?;
+        let combined: Value = client.octocrab().get(route("status"), None::<&()>).await?;
+        let checks = check_runs["check_runs"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|run| {
+                (
+                    run["name"].as_str().unwrap_or_default(),
+                    Self::check_run_of(run["conclusion"].as_str()),
+                )
+            })
+            .chain(
+                combined["statuses"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .map(|status| {
+                        (
+                            status["context"].as_str().unwrap_or_default(),
+                            Self::check_of(status["state"].as_str().unwrap_or_default()),
+                        )
+                    }),
+            )
+            .collect::<Vec<_>>();
+        let (checks, failing_checks) = Self::summarize_checks(checks);
+
+        let mergeable = match (pr.mergeable, &pr.mergeable_state) {
+            (Some(false), _) => Mergeability::Conflicting,
+            (_, Some(MergeableState::Behind)) => Mergeability::Behind,
+            (Some(true), _) => Mergeability::Mergeable,
+            (None, _) => Mergeability::Unknown,
+        };
+
+        Ok(Self {
+            number,
+            title: pr.title.clone().unwrap_or_default(),
+            body: pr.body.clone().unwrap_or_default(),
+            state,
+            base_ref: pr.base.ref_field.clone(),
+            head_ref: pr.head.ref_field.clone(),
+            head_oid,
+            review,
+            mergeable,
+            checks,
+            failing_checks,
+            unresolved_threads: None,
+        })
+    }
+
+    /// Returns the [PullRequestState] of a pull request fetched through the REST API.
+    pub fn state_of(pr: &PullRequest) -> PullRequestState {
+        if pr.merged_at.is_some() {
+            PullRequestState::Merged
+        } else if matches!(pr.state, Some(IssueState::Closed)) {
+            PullRequestState::Closed
+        } else if pr.draft.unwrap_or_default() {
+            PullRequestState::Draft
+        } else {
+            PullRequestState::Open
+        }
+    }
+
+    /// Computes the review decision from the latest review of each reviewer, as GitHub does.
+    fn review_of<'a>(
+        latest: impl IntoIterator<Item = &'a ReviewState>,
+        requested: bool,
+    ) -> ReviewStatus {
+        let latest = latest.into_iter().collect::<Vec<_>>();
+        if latest.iter().any(|s| **s == ReviewState::ChangesRequested) {
+            ReviewStatus::ChangesRequested
+        } else if latest.iter().any(|s| **s == ReviewState::Approved) {
+            ReviewStatus::Approved
+        } else if requested {
+            ReviewStatus::ReviewRequired
+        } else {
+            ReviewStatus::None
+        }
+    }
+
+    /// Maps the state of a commit status or check rollup to a [CheckStatus].
+    fn check_of(state: &str) -> CheckStatus {
+        match state.to_ascii_uppercase().as_str() {
+            "SUCCESS" => CheckStatus::Passing,
+            "FAILURE" | "ERROR" => CheckStatus::Failing,
+            _ => CheckStatus::Pending,
+        }
+    }
+
+    /// Maps the conclusion of a check run to a [CheckStatus]. Check runs have no conclusion until
+    /// they complete.
+    fn check_run_of(conclusion: Option<&str>) -> CheckStatus {
+        match conclusion.map(str::to_ascii_uppercase).as_deref() {
+            None => CheckStatus::Pending,
+            Some("SUCCESS" | "NEUTRAL" | "SKIPPED") => CheckStatus::Passing,
+            Some(_) => CheckStatus::Failing,
+        }
+    }
+
+    /// Combines named checks into their rollup and the names of the failing ones.
+    fn summarize_checks<'a>(
+        checks: impl IntoIterator<Item = (&'a str, CheckStatus)>,
+    ) -> (CheckStatus, Vec<String>) {
+        let checks = checks.into_iter().collect::<Vec<_>>();
+        let failing = checks
+            .iter()
+            .filter(|(_, status)| *status == CheckStatus::Failing)
+            .map(|(name, _)| name.to_string())
+            .collect();
+        (
+            CheckStatus::rollup(checks.into_iter().map(|(_, status)| status)),
+            failing,
+        )
+    }
+
+    /// Builds the GraphQL query for the status of the given pull requests, aliased by number.
+    fn graphql_query(numbers: &[u64]) -> String {
+        let pulls = numbers
+            .iter()
+            .map(|n| format!("pr{n}: pullRequest(number: {n}) {{ {PULL_REQUEST_FIELDS} }}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        format!(
+            "query($owner: String!, $repo: String!) {{ repository(owner: $owner, name: $repo) {{ {pulls} }} }}"
+        )
+    }
+
+    /// Parses the response to [Self::graphql_query].
+    fn from_graphql(data: &Value, numbers: &[u64]) -> StResult<Vec<Self>> {
+        numbers
+            .iter()
+            .map(|n| {
+                let pr = data["repository"]
+                    .get(format!("pr{n}"))
+                    .filter(|pr| !pr.is_null())
+                    .ok_or(StError::PullRequestNotFound)?;
+                let pr = GraphQLPullRequest::deserialize(pr)
+                    .map_err(|e| StError::GraphQLSchemaError(e.to_string()))?;
+
+                let state = match (pr.state.as_str(), pr.merged, pr.is_draft) {
+                    (_, true, _) => PullRequestState::Merged,
+                    ("CLOSED", _, _) => PullRequestState::Closed,
+                    (_, _, true) => PullRequestState::Draft,
+                    _ => PullRequestState::Open,
+                };
+                let review = match pr.review_decision.as_deref() {
+                    Some("APPROVED") => ReviewStatus::Approved,
+                    Some("CHANGES_REQUESTED") => ReviewStatus::ChangesRequested,
+                    Some("REVIEW_REQUIRED") => ReviewStatus::ReviewRequired,
+                    _ => ReviewStatus::None,
+                };
+                let mergeable = match (pr.mergeable.as_str(), pr.merge_state_status.as_str()) {
+                    ("CONFLICTING", _) | (_, "DIRTY") => Mergeability::Conflicting,
+                    (_, "BEHIND") => Mergeability::Behind,
+                    ("MERGEABLE", _) => Mergeability::Mergeable,
+                    _ => Mergeability::Unknown,
+                };
+                let rollup = pr
+                    .commits
+                    .nodes
+                    .first()
+                    .and_then(|node| node.commit.status_check_rollup.as_ref());
+                let checks =
+                    rollup.map_or(CheckStatus::None, |rollup| Self::check_of(&rollup.state));
+                let (_, failing_checks) = Self::summarize_checks(
+                    rollup
+                        .into_iter()
+                        .flat_map(|rollup| rollup.contexts.nodes.iter())
+                        .map(|context| match &context.state {
+                            Some(state) => (
+                                context.context.as_deref().unwrap_or_default(),
+                                Self::check_of(state),
+                            ),
+                            None => (
+                                context.name.as_deref().unwrap_or_default(),
+                                Self::check_run_of(context.conclusion.as_deref()),
+                            ),
+                        }),
+                );
+                let unresolved_threads = pr
+                    .review_threads
+                    .nodes
+                    .iter()
+                    .filter(|thread| !thread.is_resolved)
+                    .count() as u64;
+
+                Ok(Self {
+                    number: pr.number,
+                    title: pr.title,
+                    body: pr.body,
+                    state,
+                    base_ref: pr.base_ref_name,
+                    head_ref: pr.head_ref_name,
+                    head_oid: pr.head_ref_oid,
+                    review,
+                    mergeable,
+                    checks,
+                    failing_checks,
+                    unresolved_threads: Some(unresolved_threads),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CheckStatus, Mergeability, PullRequestState, PullRequestStatus, ReviewStatus};
+
+    #[test]
+    fn parses_batched_graphql_response() {
+        let query = PullRequestStatus::graphql_query(&[3, 5]);
+        assert!(query.contains("pr3: pullRequest(number: 3)"));
+        assert!(query.contains("pr5: pullRequest(number: 5)"));
+
+        let pr = |number: u64, state: &str, merged: bool, rollup: Option<&str>| {
+            let contexts = serde_json::json!({ "nodes": [
+                { "name": "lint", "conclusion": "SUCCESS" },
+                { "name": "test", "conclusion": "FAILURE" },
+                { "name": "build", "conclusion": null },
+                { "context": "ci/deploy", "state": "ERROR" },
+            ] });
+            serde_json::json!({
+                "number": number,
+                "title": format!("PR {number}"),
+                "body": "",
+                "state": state,
+                "isDraft": false,
+                "merged": merged,
+                "baseRefName": "main",
+                "headRefName": format!("feat-{number}"),
+                "headRefOid": "abc",
+                "reviewDecision": if merged { None } else { Some("CHANGES_REQUESTED") },
+                "mergeable": if merged { "UNKNOWN" } else { "MERGEABLE" },
+                "mergeStateStatus": if merged { "UNKNOWN" } else { "BEHIND" },
+                "reviewThreads": { "nodes": [{ "isResolved": true }, { "isResolved": false }] },
+                "commits": { "nodes": [{ "commit": {
+                    "statusCheckRollup": rollup.map(|state| {
+                        serde_json::json!({ "state": state, "contexts": contexts })
+                    })
+                } }] },
+            })
+        };
+        let data = serde_json::json!({ "repository": {
+            "pr3": pr(3, "OPEN", false, Some("FAILURE")),
+            "pr5": pr(5, "MERGED", true, None),
+        } });
+
+        let statuses = PullRequestStatus::from_graphql(&data, &[5, 3]).unwrap();
+        assert_eq!(statuses[0].number, 5);
+        assert_eq!(statuses[0].state, PullRequestState::Merged);
+        assert_eq!(statuses[0].review, ReviewStatus::None);
+        assert_eq!(statuses[0].checks, CheckStatus::None);
+        assert!(statuses[0].failing_checks.is_empty());
+        assert_eq!(statuses[1].state, PullRequestState::Open);
+        assert_eq!(statuses[1].head_ref, "feat-3");
+        assert_eq!(statuses[1].review, ReviewStatus::ChangesRequested);
+        assert_eq!(statuses[1].mergeable, Mergeability::Behind);
+        assert_eq!(statuses[1].checks, CheckStatus::Failing);
+        assert_eq!(statuses[1].failing_checks, ["test", "ci/deploy"]);
+        assert_eq!(statuses[1].unresolved_threads, Some(1));
+
+        assert!(PullRequestStatus::from_graphql(&data, &[4]).is_err());
+    }
+
+    #[test]
+    fn rolls_up_checks() {
+        use CheckStatus::*;
+
+        #[rustfmt::skip]
+        let cases: [(&[CheckStatus], CheckStatus); 4] = [
+            (&[], None),
+            (&[Passing, Passing], Passing),
+            (&[Passing, Pending], Pending),
+            (&[Pending, Failing, Passing], Failing),
+        ];
+        for (checks, expected) in cases {
+            assert_eq!(CheckStatus::rollup(checks.iter().copied()), expected);
+        }
+    }
+}
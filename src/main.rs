@@ -6,11 +6,14 @@
 use clap::Parser;
 
 mod cli;
+mod codeowners;
 mod config;
 mod constants;
 mod ctx;
 mod errors;
 mod git;
+mod github;
+mod remote_url;
 mod subcommands;
 mod tree;
 
@@ -36,8 +36,91 @@ impl CheckoutCmd {
             return Err(StError::BranchNotTracked(branch_name));
         }
 
+        // Ensure the provided branch is not checked out in another worktree.
+        if let Some(path) = ctx.repository.checked_out_elsewhere(&branch_name)? {
+            return Err(StError::BranchCheckedOutInWorktree(branch_name, path));
+        }
+
         ctx.repository
             .checkout_branch(branch_name.as_str())
             .map_err(Into::into)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::CheckoutCmd;
+    use crate::{
+        config::{MetadataBackend, StConfig},
+        constants::ST_METADATA_REF,
+        ctx::{ctx_path, StContext},
+        errors::StError,
+        github::fixtures::git,
+    };
+    use git2::Repository;
+
+    #[test]
+    fn shares_the_stack_and_respects_other_worktrees() {
+        let dir = tempfile::tempdir().unwrap();
+        let (work, linked) = (dir.path().join("work"), dir.path().join("linked"));
+        git(dir.path(), &["init", "-b", "main", "work"]);
+        git(&work, &["commit", "--allow-empty", "-m", "base"]);
+        git(&work, &["branch", "a"]);
+        git(&work, &["branch", "b"]);
+        git(&work, &["worktree", "add", linked.to_str().unwrap(), "a"]);
+        let oid = git(&work, &["rev-parse", "main"]);
+
+        let (main_repo, linked_repo) = (
+            Repository::open(&work).unwrap(),
+            Repository::open(&linked).unwrap(),
+        );
+        let track = |repository, cfg| {
+            let mut ctx = StContext::fresh(cfg, repository, "main".to_string());
+            ctx.tree.insert("main", &oid, "a").unwrap();
+            ctx.tree.insert("main", &oid, "b").unwrap();
+            ctx
+        };
+
+        // Both checkouts resolve the same store and metadata ref.
+        drop(track(&linked_repo, StConfig::default()));
+        assert_eq!(
+            ctx_path(&main_repo).canonicalize().unwrap(),
+            ctx_path(&linked_repo).canonicalize().unwrap()
+        );
+        let ctx = StContext::try_load(StConfig::default(), &main_repo)
+            .unwrap()
+            .unwrap();
+        assert!(ctx.tree.get("a").is_some());
+        drop(ctx);
+
+        let cfg = StConfig {
+            metadata_backend: MetadataBackend::Ref,
+            ..Default::default()
+        };
+        drop(track(&linked_repo, cfg.clone()));
+        assert_eq!(
+            main_repo.refname_to_id(ST_METADATA_REF).unwrap(),
+            linked_repo.refname_to_id(ST_METADATA_REF).unwrap()
+        );
+
+        // `a` is checked out in the linked worktree, so it can be neither checked out nor deleted
+        // from the main one.
+        let checked_out_in_linked = |result| {
+            matches!(result, Err(StError::BranchCheckedOutInWorktree(ref branch, ref path))
+                if branch == "a"
+                    && path.canonicalize().unwrap() == linked.canonicalize().unwrap())
+        };
+        let checkout = |branch: &str| CheckoutCmd {
+            branch_name: Some(branch.to_string()),
+        };
+        assert!(checked_out_in_linked(
+            checkout("a").run(track(&main_repo, cfg.clone()))
+        ));
+        assert!(checked_out_in_linked(
+            track(&main_repo, cfg.clone()).delete_branch("a", false)
+        ));
+
+        checkout("b").run(track(&main_repo, cfg)).unwrap();
+        assert_eq!(git(&work, &["branch", "--show-current"]), "b");
+    }
+}
@@ -1,17 +1,155 @@
 //! `config` subcommand.
 
-use crate::{config::prompt_for_configuration, ctx::StContext, errors::StResult};
+use crate::{
+    config::{
+        parse_value, prompt_for_configuration, set_value, ConfigScope, LayeredConfig,
+        StConfigError, TokenSource,
+    },
+    ctx::StContext,
+    errors::StResult,
+};
+use clap::{Args, Subcommand};
+use nu_ansi_term::Color;
+use toml::Value;
 
-#[derive(Debug, Clone, Eq, PartialEq, clap::Args)]
-pub struct ConfigCmd;
+/// CLI arguments for the `config` subcommand.
+#[derive(Debug, Clone, Eq, PartialEq, Args)]
+pub struct ConfigCmd {
+    /// The configuration operation to perform. Opens the configuration file in an editor if
+    /// omitted.
+    #[clap(subcommand)]
+    action: Option<ConfigAction>,
+    /// The configuration file to edit.
+    #[clap(flatten)]
+    scope: ScopeArgs,
+}
+
+/// Operations on the layered configuration.
+#[derive(Debug, Clone, Eq, PartialEq, Subcommand)]
+pub enum ConfigAction {
+    /// Print the resolved value of a configuration key.
+    Get {
+        /// The dotted configuration key, e.g. `remote` or `hosts."github.example.com".api_url`.
+        #[clap(index = 1)]
+        key: String,
+        /// Print the source that the value came from.
+        #[clap(long)]
+        show_origin: bool,
+    },
+    /// Set a configuration key in one of the configuration files.
+    Set {
+        /// The dotted configuration key.
+        #[clap(index = 1)]
+        key: String,
+        /// The value, as a TOML literal. Values that are not valid TOML are treated as strings.
+        #[clap(index = 2)]
+        value: String,
+        /// The configuration file to write to.
+        #[clap(flatten)]
+        scope: ScopeArgs,
+    },
+    /// List all resolved configuration values.
+    #[clap(visible_alias = "ls")]
+    List {
+        /// Print the source that each value came from.
+        #[clap(long)]
+        show_origin: bool,
+    },
+}
+
+/// Selection of the configuration file to operate on. Defaults to the global configuration.
+#[derive(Debug, Clone, Eq, PartialEq, Args)]
+pub struct ScopeArgs {
+    /// Use the configuration of the current repository, within the git directory.
+    #[clap(long, conflicts_with = "team")]
+    repo: bool,
+    /// Use the team configuration, `.st/config.toml` in the root of the repository.
+    #[clap(long)]
+    team: bool,
+}
+
+impl ScopeArgs {
+    /// Returns the selected [ConfigScope].
+    fn scope(&self) -> ConfigScope {
+        if self.repo {
+            ConfigScope::Repo
+        } else if self.team {
+            ConfigScope::Team
+        } else {
+            ConfigScope::Global
+        }
+    }
+}
 
 impl ConfigCmd {
-    /// Run the `config` subcommand to force or allow configuration editing.
-    pub fn run(self, mut ctx: StContext<'_>) -> StResult<()> {
-        let ser = toml::to_string_pretty(&ctx.cfg)?;
-        let cfg = prompt_for_configuration(Some(&ser))?;
-        ctx.cfg = cfg;
+    /// Run the `config` subcommand to inspect or edit the configuration.
+    pub fn run(self, ctx: StContext<'_>) -> StResult<()> {
+        match self.action {
+            None => {
+                prompt_for_configuration(ctx.repository, self.scope.scope())?;
+            }
+            Some(ConfigAction::Get { key, show_origin }) => {
+                let (value, origin) = LayeredConfig::load(ctx.repository)?
+                    .get(&key)?
+                    .ok_or(StConfigError::UnsetKey(key))?;
+                if show_origin {
+                    println!("{}\t{}", origin, format_value(&value));
+                } else {
+                    println!("{}", format_value(&value));
+                }
+            }
+            Some(ConfigAction::Set { key, value, scope }) => {
+                let scope = scope.scope();
+                let path = set_value(ctx.repository, scope, &key, parse_value(&value))?;
+                println!(
+                    "Set `{}` in the {} configuration at `{}`.",
+                    Color::Blue.paint(&key),
+                    scope,
+                    path.display()
+                );
+            }
+            Some(ConfigAction::List { show_origin }) => {
+                let print = |key: &str, value: &str, origin: &str, comment: &str| {
+                    if show_origin {
+                        println!("{}\t{} = {}{}", origin, key, value, comment);
+                    } else {
+                        println!("{} = {}{}", key, value, comment);
+                    }
+                };
+                let active = format!("  # used for {}", ctx.cfg.token_host);
+
+                for (key, value, origin) in LayeredConfig::load(ctx.repository)?.entries() {
+                    // Keep tokens out of terminal scrollback; `get` prints them for scripts.
+                    if key == "github_token" || key.ends_with(".token") {
+                        let is_active = matches!(
+                            &ctx.cfg.token_source,
+                            TokenSource::Config { origin: o, key: k } if *o == origin && *k == key
+                        );
+                        let comment = if is_active { active.as_str() } else { "" };
+                        print(&key, "<redacted>", &origin, comment);
+                    } else {
+                        print(&key, &value.to_string(), &origin, "");
+                    }
+                }
 
+                // Tokens of credential helpers and the `gh` CLI are not part of any layer.
+                if matches!(
+                    ctx.cfg.token_source,
+                    TokenSource::GitCredential | TokenSource::GhCli
+                ) {
+                    let origin = ctx.cfg.token_source.to_string();
+                    print("github_token", "<redacted>", &origin, &active);
+                }
+            }
+        }
         Ok(())
     }
 }
+
+/// Formats a value for scripts, printing strings without quotes.
+fn format_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        value => value.to_string(),
+    }
+}
@@ -85,9 +85,10 @@ impl CreateCmd {
         )?;
 
         println!(
-            "Successfully created and tracked new branch `{}` on top of `{}`",
+            "Successfully created and tracked new branch `{}` on top of `{}` (trunk: `{}`)",
             Color::Blue.paint(&new_branch_name),
-            Color::Blue.paint(&current_branch_name)
+            Color::Blue.paint(&current_branch_name),
+            Color::Blue.paint(ctx.tree.trunk_of(&new_branch_name)?)
         );
         Ok(())
     }
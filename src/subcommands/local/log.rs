@@ -1,16 +1,38 @@
 //! `log` subcommand.
 
-use crate::{ctx::StContext, errors::StResult};
+use crate::{ctx::StContext, errors::StResult, tree::unix_timestamp};
 use clap::Args;
 
 /// CLI arguments for the `log` subcommand.
 #[derive(Debug, Clone, Eq, PartialEq, Args)]
-pub struct LogCmd;
+pub struct LogCmd {
+    /// Refresh the status of the pull requests from GitHub, rather than showing the last-fetched
+    /// status.
+    #[clap(long, short)]
+    status: bool,
+}
 
 impl LogCmd {
     /// Run the `log` subcommand.
-    pub fn run(self, ctx: StContext<'_>) -> StResult<()> {
-        ctx.print_tree()?;
-        Ok(())
+    pub async fn run(self, mut ctx: StContext<'_>) -> StResult<()> {
+        if self.status {
+            let gh_client = ctx.github_client()?;
+            let branches = ctx.tree.branches()?;
+            ctx.refresh_pull_requests(&gh_client, &branches).await?;
+        }
+
+        // Annotate each submitted branch with the last-fetched status of its pull request.
+        let now = unix_timestamp();
+        let annotations = ctx
+            .tree
+            .branches
+            .iter()
+            .filter_map(|(name, branch)| {
+                let remote = branch.remote.as_ref()?;
+                let cache = remote.cache.as_ref()?;
+                Some((name.clone(), cache.annotation(remote.pr_number, now)))
+            })
+            .collect();
+        ctx.print_annotated_tree(&annotations)
     }
 }
@@ -23,3 +23,9 @@ pub use untrack::UntrackCmd;
 
 mod config;
 pub use config::ConfigCmd;
+
+mod trunk;
+pub use trunk::TrunkCmd;
+
+mod worktree;
+pub use worktree::WorktreeCmd;
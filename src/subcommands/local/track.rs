@@ -45,9 +45,10 @@ impl TrackCmd {
         ctx.restack()?;
 
         println!(
-            "Tracked branch `{}` on top of `{}`",
+            "Tracked branch `{}` on top of `{}` (trunk: `{}`)",
             Color::Green.paint(&current_branch_name),
-            Color::Yellow.paint(&parent_branch_name.branch_name)
+            Color::Yellow.paint(&parent_branch_name.branch_name),
+            Color::Blue.paint(ctx.tree.trunk_of(&current_branch_name)?)
         );
         Ok(())
     }
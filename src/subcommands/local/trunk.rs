@@ -0,0 +1,113 @@
+//! `trunk` subcommand.
+
+use crate::{
+    ctx::StContext,
+    errors::{StError, StResult},
+};
+use clap::{Args, Subcommand};
+use git2::BranchType;
+use nu_ansi_term::Color;
+
+/// CLI arguments for the `trunk` subcommand.
+#[derive(Debug, Clone, Eq, PartialEq, Args)]
+pub struct TrunkCmd {
+    /// The trunk operation to perform.
+    #[clap(subcommand)]
+    action: TrunkAction,
+}
+
+/// Operations on the trunk branches of the repository.
+#[derive(Debug, Clone, Eq, PartialEq, Subcommand)]
+pub enum TrunkAction {
+    /// Track a local branch as an additional trunk.
+    Add {
+        /// Name of the branch to track as a trunk.
+        #[clap(index = 1)]
+        branch_name: Option<String>,
+    },
+    /// Stop tracking an additional trunk.
+    #[clap(visible_alias = "rm")]
+    Remove {
+        /// Name of the trunk to remove.
+        #[clap(index = 1)]
+        branch_name: Option<String>,
+    },
+    /// List all trunk branches.
+    #[clap(visible_alias = "ls")]
+    List,
+}
+
+impl TrunkCmd {
+    /// Run the `trunk` subcommand.
+    pub fn run(self, mut ctx: StContext<'_>) -> StResult<()> {
+        match self.action {
+            TrunkAction::Add { branch_name } => {
+                // Prompt the user for one of the untracked local branches, or use the provided name.
+                let branch_name = match branch_name {
+                    Some(name) => name,
+                    None => {
+                        let branches = ctx
+                            .repository
+                            .branches(Some(BranchType::Local))?
+                            .map(|b| {
+                                let (b, _) = b?;
+                                b.name()?
+                                    .map(ToOwned::to_owned)
+                                    .ok_or(StError::BranchUnavailable)
+                            })
+                            .filter(|b| !b.as_ref().is_ok_and(|b| ctx.tree.get(b).is_some()))
+                            .collect::<StResult<Vec<_>>>()?;
+                        inquire::Select::new("Select a branch to track as a trunk", branches)
+                            .prompt()?
+                    }
+                };
+
+                // Ensure the branch exists locally.
+                ctx.repository
+                    .find_branch(&branch_name, BranchType::Local)
+                    .map_err(|_| StError::BranchUnavailable)?;
+
+                ctx.tree.add_trunk(&branch_name)?;
+                println!(
+                    "Tracked branch `{}` as a trunk.",
+                    Color::Blue.paint(&branch_name)
+                );
+            }
+            TrunkAction::Remove { branch_name } => {
+                let branch_name = match branch_name {
+                    Some(name) => name,
+                    None => {
+                        let trunks = ctx
+                            .tree
+                            .trunks()
+                            .into_iter()
+                            .skip(1)
+                            .map(ToOwned::to_owned)
+                            .collect();
+                        inquire::Select::new("Select a trunk to remove", trunks).prompt()?
+                    }
+                };
+
+                ctx.tree.remove_trunk(&branch_name)?;
+                println!(
+                    "Successfully removed trunk `{}`.",
+                    Color::Blue.paint(&branch_name)
+                );
+            }
+            TrunkAction::List => {
+                ctx.tree
+                    .trunks()
+                    .into_iter()
+                    .enumerate()
+                    .for_each(|(i, trunk)| {
+                        println!(
+                            "{}{}",
+                            Color::Blue.paint(trunk),
+                            if i == 0 { " (default)" } else { "" }
+                        )
+                    });
+            }
+        }
+        Ok(())
+    }
+}
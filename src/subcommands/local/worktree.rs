@@ -0,0 +1,362 @@
+//! `worktree` subcommand.
+
+use crate::{
+    ctx::StContext,
+    errors::{StError, StResult},
+    git::RepositoryExt,
+    github::PullRequestState,
+};
+use clap::{Args, Subcommand};
+use git2::{
+    BranchType, Oid, Repository, StatusOptions, StatusShow, Worktree, WorktreeAddOptions,
+    WorktreeLockStatus, WorktreePruneOptions,
+};
+use nu_ansi_term::Color;
+use std::{collections::HashMap, path::PathBuf};
+
+/// CLI arguments for the `worktree` subcommand.
+#[derive(Debug, Clone, Eq, PartialEq, Args)]
+pub struct WorktreeCmd {
+    /// The worktree operation to perform.
+    #[clap(subcommand)]
+    action: WorktreeAction,
+}
+
+/// Operations on the worktrees of tracked branches.
+#[derive(Debug, Clone, Eq, PartialEq, Subcommand)]
+pub enum WorktreeAction {
+    /// Create a linked worktree for a tracked branch.
+    Add {
+        /// Name of the tracked branch to create a worktree for.
+        #[clap(index = 1)]
+        branch_name: Option<String>,
+        /// Path of the new worktree. Defaults to a directory named after the branch, within the
+        /// configured worktree directory.
+        #[clap(long, short)]
+        path: Option<PathBuf>,
+    },
+    /// Print a tree of all tracked stacks, annotated with the worktree of each branch.
+    #[clap(visible_alias = "ls")]
+    List,
+    /// Remove the worktrees of branches that have been deleted or merged.
+    Prune,
+}
+
+impl WorktreeCmd {
+    /// Run the `worktree` subcommand.
+    pub fn run(self, ctx: StContext<'_>) -> StResult<()> {
+        match self.action {
+            WorktreeAction::Add { branch_name, path } => Self::add(ctx, branch_name, path),
+            WorktreeAction::List => Self::list(ctx),
+            WorktreeAction::Prune => Self::prune(ctx),
+        }
+    }
+
+    /// Creates a linked worktree for a tracked branch.
+    fn add(ctx: StContext<'_>, branch_name: Option<String>, path: Option<PathBuf>) -> StResult<()> {
+        // Prompt the user for the name of the branch, or use the provided name.
+        let branch_name = match branch_name {
+            Some(name) => name,
+            None => {
+                inquire::Select::new(
+                    "Select a branch to open a worktree for",
+                    ctx.display_branches()?,
+                )
+                .with_formatter(&|f| f.value.branch_name.clone())
+                .prompt()?
+                .branch_name
+            }
+        };
+
+        // Ensure the branch is tracked with `st`, and not checked out in any worktree.
+        if ctx.tree.get(&branch_name).is_none() {
+            return Err(StError::BranchNotTracked(branch_name));
+        }
+        if let Some(path) = ctx.repository.worktree_branches()?.remove(&branch_name) {
+            return Err(StError::BranchCheckedOutInWorktree(branch_name, path));
+        }
+
+        // Worktree names may not contain path separators.
+        let worktree_name = branch_name.replace('/', "-");
+        let path = match path {
+            Some(path) => path,
+            None => Self::worktree_dir(&ctx)?.join(&worktree_name),
+        };
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let reference = ctx
+            .repository
+            .find_branch(&branch_name, BranchType::Local)?
+            .into_reference();
+        ctx.repository.worktree(
+            &worktree_name,
+            &path,
+            Some(WorktreeAddOptions::new().reference(Some(&reference))),
+        )?;
+
+        println!(
+            "Created worktree for branch `{}` at `{}`.",
+            Color::Green.paint(&branch_name),
+            Color::Blue.paint(path.display().to_string())
+        );
+        Ok(())
+    }
+
+    /// Prints the tree of tracked branches, annotated with the worktree of each branch.
+    fn list(ctx: StContext<'_>) -> StResult<()> {
+        ctx.print_annotated_tree(&Self::annotations(&ctx)?)
+    }
+
+    /// Returns the path of the worktree of each branch that is checked out, keyed by branch name.
+    fn annotations(ctx: &StContext<'_>) -> StResult<HashMap<String, String>> {
+        Ok(ctx
+            .repository
+            .worktree_branches()?
+            .into_iter()
+            .map(|(branch, path)| (branch, path.display().to_string()))
+            .collect())
+    }
+
+    /// Removes the linked worktrees of branches that have been deleted or merged into their trunk.
+    fn prune(ctx: StContext<'_>) -> StResult<()> {
+        let mut num_pruned = 0;
+        for (worktree, branch, reason) in Self::prunable(&ctx)? {
+            let confirm = inquire::Confirm::new(
+                format!(
+                    "Branch `{}` was {}. Remove its worktree at `{}`?",
+                    Color::Green.paint(&branch),
+                    Color::Purple.bold().paint(reason),
+                    worktree.path().display()
+                )
+                .as_str(),
+            )
+            .with_default(false)
+            .prompt()?;
+
+            if confirm {
+                worktree.prune(Some(
+                    WorktreePruneOptions::new().valid(true).working_tree(true),
+                ))?;
+                num_pruned += 1;
+            }
+        }
+
+        println!(
+            "Removed {} worktree{}.",
+            Color::Red.paint(num_pruned.to_string()),
+            if num_pruned != 1 { "s" } else { "" }
+        );
+        Ok(())
+    }
+
+    /// Finds the linked worktrees whose tracked branch has been deleted or merged into its trunk,
+    /// and that have no uncommitted changes. The administrative files of worktrees whose directory
+    /// no longer exists are cleaned up along the way.
+    ///
+    /// ## Returns
+    /// - `Result<Vec<(Worktree, String, &str)>>` - The worktrees, with the name of their branch
+    ///   and the reason they can be removed.
+    fn prunable(ctx: &StContext<'_>) -> StResult<Vec<(Worktree, String, &'static str)>> {
+        let mut prunable = Vec::new();
+        for name in ctx.repository.worktrees()?.iter().flatten() {
+            let worktree = ctx.repository.find_worktree(name)?;
+
+            // Clean up the administrative files of worktrees whose directory no longer exists.
+            if worktree.validate().is_err() {
+                worktree.prune(None)?;
+                continue;
+            }
+
+            // Leave locked worktrees alone.
+            if !matches!(worktree.is_locked()?, WorktreeLockStatus::Unlocked) {
+                continue;
+            }
+
+            // Skip worktrees that do not have a branch checked out.
+            let worktree_repo = Repository::open_from_worktree(&worktree)?;
+            let Some(branch) = worktree_repo
+                .find_reference("HEAD")?
+                .symbolic_target()
+                .and_then(|target| target.strip_prefix("refs/heads/"))
+                .map(ToOwned::to_owned)
+            else {
+                continue;
+            };
+
+            // Only branches that `st` tracked are considered deleted, as unborn branches and
+            // branches that were never tracked are missing as well.
+            let exists = ctx
+                .repository
+                .find_branch(&branch, BranchType::Local)
+                .is_ok();
+            let reason = if !exists && ctx.pruned_branches().contains(&branch) {
+                "deleted"
+            } else if exists && ctx.tree.get(&branch).is_some() && Self::is_merged(ctx, &branch)? {
+                "merged"
+            } else {
+                continue;
+            };
+
+            // Never discard uncommitted work. The index of a worktree whose branch was deleted is
+            // compared against an unborn `HEAD`, so only its working directory is checked.
+            let mut status_opts = StatusOptions::new();
+            status_opts.include_untracked(true);
+            if !exists {
+                status_opts.show(StatusShow::Workdir);
+            }
+            if !worktree_repo.statuses(Some(&mut status_opts))?.is_empty() {
+                println!(
+                    "Worktree at `{}` has uncommitted changes. Skipping.",
+                    worktree.path().display()
+                );
+                continue;
+            }
+
+            prunable.push((worktree, branch, reason));
+        }
+        Ok(prunable)
+    }
+
+    /// Returns the directory in which new worktrees are created.
+    fn worktree_dir(ctx: &StContext<'_>) -> StResult<PathBuf> {
+        // Resolve the root of the main worktree, or of the bare repository.
+        let main = Repository::open(ctx.repository.common_dir())?;
+        let root = main.workdir().unwrap_or(main.path());
+        let root = root.canonicalize().unwrap_or(root.to_path_buf());
+
+        match ctx.cfg.worktree_dir.as_ref() {
+            Some(dir) => Ok(root.join(dir)),
+            None => {
+                let name = root
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                Ok(root.with_file_name(format!("{}.worktrees", name)))
+            }
+        }
+    }
+
+    /// Returns whether the branch has commits of its own that are all contained in its trunk, or
+    /// whether its pull request was last seen merged. The latter catches squash and rebase merges,
+    /// which leave the commits of the branch out of the trunk. A branch without commits of its
+    /// own, e.g. one that was just created, is never merged.
+    fn is_merged(ctx: &StContext<'_>, branch_name: &str) -> StResult<bool> {
+        let pr_merged = ctx
+            .tree
+            .get(branch_name)
+            .and_then(|b| b.remote.as_ref()?.cache.as_ref())
+            .is_some_and(|cache| cache.state == PullRequestState::Merged);
+        if pr_merged {
+            return Ok(true);
+        }
+
+        let tip = |name: &str| {
+            ctx.repository
+                .find_branch(name, BranchType::Local)?
+                .get()
+                .target()
+                .ok_or(StError::BranchUnavailable)
+        };
+        let (branch, trunk) = (tip(branch_name)?, tip(ctx.tree.trunk_of(branch_name)?)?);
+
+        // The branch has commits of its own if it is not contained in the commit it was based on.
+        let Some(base) = ctx
+            .tree
+            .get(branch_name)
+            .and_then(|b| b.parent_oid_cache.as_deref())
+        else {
+            return Ok(false);
+        };
+        let base = Oid::from_str(base)?;
+        let has_own_commits = ctx.repository.merge_base(branch, base)? != branch;
+
+        Ok(has_own_commits && ctx.repository.graph_descendant_of(trunk, branch)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::WorktreeCmd;
+    use crate::{config::StConfig, ctx::StContext, github::fixtures::git};
+    use git2::Repository;
+    use std::path::Path;
+
+    /// Tracks `merged` and `fresh` on top of `main`, with the stored state of `main` they were
+    /// based on.
+    fn track<'a>(repository: &'a Repository, base: &str) -> StContext<'a> {
+        let mut ctx = StContext::fresh(StConfig::default(), repository, "main".to_string());
+        ctx.tree.insert("main", base, "merged").unwrap();
+        ctx.tree.insert("main", base, "fresh").unwrap();
+        ctx
+    }
+
+    /// Returns the branches whose worktrees `prune` offers to remove, with the reason.
+    fn prunable(repository: &Repository, base: &str) -> Vec<(String, &'static str)> {
+        WorktreeCmd::prunable(&track(repository, base))
+            .unwrap()
+            .into_iter()
+            .map(|(_, branch, reason)| (branch, reason))
+            .collect()
+    }
+
+    #[test]
+    fn adds_lists_and_prunes_worktrees() {
+        let dir = tempfile::tempdir().unwrap();
+        let work = dir.path().join("work");
+        git(dir.path(), &["init", "-b", "main", "work"]);
+        git(&work, &["commit", "--allow-empty", "-m", "base"]);
+        let base = git(&work, &["rev-parse", "main"]);
+
+        // `merged` has a commit of its own, while `fresh` was just created.
+        git(&work, &["branch", "fresh"]);
+        git(&work, &["checkout", "-b", "merged"]);
+        git(&work, &["commit", "--allow-empty", "-m", "Add merged"]);
+        git(&work, &["checkout", "main"]);
+
+        let repository = Repository::open(&work).unwrap();
+        let path = |name: &str| dir.path().join(name);
+        for branch in ["merged", "fresh"] {
+            WorktreeCmd::add(
+                track(&repository, &base),
+                Some(branch.to_string()),
+                Some(path(branch)),
+            )
+            .unwrap();
+        }
+
+        // Each tracked branch is annotated with its worktree.
+        let annotations = WorktreeCmd::annotations(&track(&repository, &base)).unwrap();
+        for branch in ["merged", "fresh"] {
+            assert_eq!(
+                Path::new(&annotations[branch]).canonicalize().unwrap(),
+                path(branch).canonicalize().unwrap()
+            );
+        }
+        assert!(WorktreeCmd::add(
+            track(&repository, &base),
+            Some("merged".to_string()),
+            Some(path("again"))
+        )
+        .is_err());
+
+        // An unborn branch checked out in a worktree is not mistaken for a deleted one.
+        let orphan = path("orphan");
+        git(
+            &work,
+            &["worktree", "add", "--detach", orphan.to_str().unwrap()],
+        );
+        git(&orphan, &["checkout", "--orphan", "unborn"]);
+        assert!(prunable(&repository, &base).is_empty());
+
+        // Once `main` moves past both branches, only `merged` has been merged into it.
+        git(&work, &["merge", "--ff-only", "merged"]);
+        git(&work, &["commit", "--allow-empty", "-m", "Add other"]);
+        assert_eq!(
+            prunable(&repository, &base),
+            [("merged".to_string(), "merged")]
+        );
+    }
+}
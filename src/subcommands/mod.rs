@@ -5,11 +5,12 @@ use clap::Subcommand;
 
 mod local;
 use local::{
-    CheckoutCmd, ConfigCmd, CreateCmd, DeleteCmd, LogCmd, RestackCmd, TrackCmd, UntrackCmd,
+    CheckoutCmd, ConfigCmd, CreateCmd, DeleteCmd, LogCmd, RestackCmd, TrackCmd, TrunkCmd,
+    UntrackCmd, WorktreeCmd,
 };
 
 mod remote;
-use remote::{StatusCmd, SubmitCmd, SyncCmd};
+use remote::{DraftCmd, MergeCmd, MetadataCmd, ReadyCmd, StatusCmd, SubmitCmd, SyncCmd};
 
 #[derive(Debug, Clone, Eq, PartialEq, Subcommand)]
 pub enum Subcommands {
@@ -19,6 +20,13 @@ pub enum Subcommands {
     /// Submit the current PR stack to GitHub.
     #[clap(visible_aliases = ["s", "ss"])]
     Submit(SubmitCmd),
+    /// Mark the pull requests of the current branch or stack as ready for review.
+    Ready(ReadyCmd),
+    /// Convert the pull requests of the current branch or stack to drafts.
+    Draft(DraftCmd),
+    /// Merge the bottom pull request of the current stack, and restack the rest onto the trunk.
+    #[clap(visible_alias = "m")]
+    Merge(MergeCmd),
     /// Checkout a branch that is tracked with `st`.
     #[clap(visible_alias = "co")]
     Checkout(CheckoutCmd),
@@ -43,9 +51,18 @@ pub enum Subcommands {
     /// Untrack the passed branch.
     #[clap(visible_alias = "ut")]
     Untrack(UntrackCmd),
+    /// Manage the trunk branches of the repository.
+    #[clap(visible_alias = "tk")]
+    Trunk(TrunkCmd),
+    /// Manage worktrees for tracked branches.
+    #[clap(visible_alias = "wt")]
+    Worktree(WorktreeCmd),
     /// Configure the st application.
     #[clap(visible_alias = "cfg")]
     Config(ConfigCmd),
+    /// Share the stack metadata with the remote.
+    #[clap(visible_alias = "meta")]
+    Metadata(MetadataCmd),
 }
 
 impl Subcommands {
@@ -55,15 +72,21 @@ impl Subcommands {
             // Remote
             Self::Sync(args) => args.run(ctx).await,
             Self::Submit(args) => args.run(ctx).await,
+            Self::Ready(args) => args.run(ctx).await,
+            Self::Draft(args) => args.run(ctx).await,
+            Self::Merge(args) => args.run(ctx).await,
             Self::Status(args) => args.run(ctx).await,
+            Self::Metadata(args) => args.run(ctx),
             // Local
             Self::Checkout(args) => args.run(ctx),
             Self::Create(args) => args.run(ctx),
             Self::Delete(args) => args.run(ctx),
             Self::Restack(args) => args.run(ctx),
-            Self::Log(args) => args.run(ctx),
+            Self::Log(args) => args.run(ctx).await,
             Self::Track(args) => args.run(ctx),
             Self::Untrack(args) => args.run(ctx),
+            Self::Trunk(args) => args.run(ctx),
+            Self::Worktree(args) => args.run(ctx),
             Self::Config(args) => args.run(ctx),
         }
     }
@@ -0,0 +1,364 @@
+//! Rendering of the stack overview comments posted on pull requests.
+
+use crate::{
+    constants::ST_COMMENT_TEMPLATE_PATH,
+    errors::{StError, StResult},
+    github::PullRequestStatus,
+    tree::StackTree,
+};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// The template used when the repository does not provide one.
+///
+/// Placeholders:
+/// - `{{stack}}` - The tree of pull requests, as a nested Markdown list.
+/// - `{{trunk}}` - The name of the trunk branch that the stack is based on.
+/// - `{{branch}}` - The name of the branch of the pull request the comment is posted on.
+/// - `{{pr}}` - The number of the pull request the comment is posted on.
+pub(crate) const DEFAULT_COMMENT_TEMPLATE: &str = "## 📚 $\\text{Stack Overview}$
+
+Pulls submitted in this stack:
+{{stack}}
+_This comment was automatically generated by [`st`](https://github.com/clabby/st)._";
+
+/// The hidden marker that identifies stack comments, to recover them when their ID is lost.
+pub(crate) const COMMENT_MARKER: &str = "<!-- st:stack-overview -->";
+
+/// The marker that opens the stack overview section of a pull request description.
+pub(crate) const BODY_SECTION_START: &str = "<!-- st:stack-overview:start -->";
+
+/// The marker that closes the stack overview section of a pull request description.
+pub(crate) const BODY_SECTION_END: &str = "<!-- st:stack-overview:end -->";
+
+/// Loads the stack comment template of the repository.
+///
+/// ## Takes
+/// - `root` - The root of the repository's working tree.
+/// - `configured` - The path of the template set in the configuration, relative to `root`.
+///
+/// ## Returns
+/// - `Result<String>` - The configured template, the template at [ST_COMMENT_TEMPLATE_PATH] if it
+///   exists, or [DEFAULT_COMMENT_TEMPLATE]. Templates that resolve to a file outside of the
+///   working tree, through `..`, an absolute path or a symbolic link, are refused.
+pub fn load_template(root: &Path, configured: Option<&Path>) -> StResult<String> {
+    match configured {
+        Some(path) => Ok(fs::read_to_string(contained_path(root, path)?)?),
+        None => match contained_path(root, Path::new(ST_COMMENT_TEMPLATE_PATH)) {
+            Ok(path) => Ok(fs::read_to_string(path)?),
+            Err(StError::IoError(_)) => Ok(DEFAULT_COMMENT_TEMPLATE.to_string()),
+            Err(e) => Err(e),
+        },
+    }
+}
+
+/// Resolves `path` relative to `root`, ensuring that it points to a file within `root`.
+fn contained_path(root: &Path, path: &Path) -> StResult<PathBuf> {
+    let resolved = root.join(path).canonicalize()?;
+    if resolved.starts_with(root.canonicalize()?) {
+        Ok(resolved)
+    } else {
+        Err(StError::CommentTemplateOutsideRepository(
+            path.to_path_buf(),
+        ))
+    }
+}
+
+/// Returns the branch directly above the trunk that `branch` descends from. The subtree of this
+/// branch holds every pull request related to `branch`, including forks.
+pub fn stack_root(tree: &StackTree, branch: &str) -> StResult<String> {
+    Ok(tree
+        .ancestors(branch)?
+        .into_iter()
+        .nth(1)
+        .unwrap_or_else(|| branch.to_string()))
+}
+
+/// Renders the stack comment of the pull request of `current_branch`.
+///
+/// ## Takes
+/// - `template` - The template to fill in.
+/// - `tree` - The stack tree.
+/// - `current_branch` - The branch of the pull request that the comment is posted on.
+/// - `statuses` - The statuses of the pull requests in the subtree, by number.
+/// - `dependent_url` - Returns a link to the changes of a branch that has not been opened as a pull
+///   request, given its parent and its name. Such branches are listed as unsubmitted if [None].
+///
+/// ## Returns
+/// - `Result<String>` - The rendered comment, or an error.
+pub fn render(
+    template: &str,
+    tree: &StackTree,
+    current_branch: &str,
+    statuses: &HashMap<u64, PullRequestStatus>,
+    dependent_url: impl Fn(&str, &str) -> Option<String>,
+) -> StResult<String> {
+    let trunk = tree.trunk_of(current_branch)?;
+    let root = stack_root(tree, current_branch)?;
+    let pr_number = tree
+        .get(current_branch)
+        .and_then(|b| b.remote.as_ref())
+        .map(|r| r.pr_number.to_string())
+        .unwrap_or_default();
+
+    // Render the trunk, followed by every branch of the subtree indented below its parent.
+    let mut stack = format!("* `{}`\n", trunk);
+    let subtree = tree.subtree(&root)?;
+    for branch in subtree.iter() {
+        let tracked_branch = tree
+            .get(branch)
+            .ok_or_else(|| StError::BranchNotTracked(branch.to_string()))?;
+        let depth = tree.ancestors(branch)?.len();
+        let parent = tracked_branch.parent.as_deref().unwrap_or_default();
+        let marker = if branch == current_branch {
+            " 👈"
+        } else {
+            ""
+        };
+
+        let entry = match &tracked_branch.remote {
+            Some(remote) => match statuses.get(&remote.pr_number) {
+                Some(status) => format!(
+                    "#{} {} · {} · {}",
+                    status.number,
+                    escape(&status.title),
+                    status.state,
+                    status.review
+                ),
+                None => format!("#{}", remote.pr_number),
+            },
+            None => match dependent_url(parent, branch) {
+                Some(url) => format!(
+                    "[`{}`]({}) _(dependent, opened once `{}` is merged)_",
+                    branch, url, parent
+                ),
+                None => format!("`{}` _(not submitted)_", branch),
+            },
+        };
+        stack.push_str(&format!("{}* {}{}\n", "  ".repeat(depth), entry, marker));
+    }
+
+    Ok(template
+        .replace("{{stack}}", &stack)
+        .replace("{{trunk}}", trunk)
+        .replace("{{branch}}", current_branch)
+        .replace("{{pr}}", &pr_number))
+}
+
+/// Returns the byte range of the stack overview section of a pull request description, markers
+/// included.
+fn body_section(body: &str) -> Option<(usize, usize)> {
+    let start = body.find(BODY_SECTION_START)?;
+    let end = body[start..].find(BODY_SECTION_END)? + start + BODY_SECTION_END.len();
+    Some((start, end))
+}
+
+/// Embeds the stack overview in a pull request description. An existing section is replaced in
+/// place, otherwise the section is appended after a blank line, in place of any trailing
+/// whitespace. The rest of the description is left untouched.
+///
+/// ## Takes
+/// - `body` - The current description of the pull request.
+/// - `overview` - The rendered stack overview.
+///
+/// ## Returns
+/// - `String` - The description with the embedded overview.
+pub fn embed_in_body(body: &str, overview: &str) -> String {
+    let section = format!("{}\n{}\n{}", BODY_SECTION_START, overview, BODY_SECTION_END);
+    match body_section(body) {
+        Some((start, end)) => format!("{}{}{}", &body[..start], section, &body[end..]),
+        None if body.trim_end().is_empty() => section,
+        None => format!("{}\n\n{}", body.trim_end(), section),
+    }
+}
+
+/// Removes the stack overview section from a pull request description, undoing [embed_in_body].
+/// A section at the end of the description is removed along with the whitespace around it, so
+/// that appending it again yields the same description.
+///
+/// ## Returns
+/// - `Some(String)` - The description without the section.
+/// - `None` - If the description has no stack overview section.
+pub fn strip_from_body(body: &str) -> Option<String> {
+    let (start, end) = body_section(body)?;
+    let (before, after) = (&body[..start], &body[end..]);
+    if after.trim().is_empty() {
+        Some(before.trim_end().to_string())
+    } else {
+        Some(format!("{}{}", before, after))
+    }
+}
+
+/// Escapes the characters of a pull request title that would otherwise be rendered as Markdown.
+fn escape(title: &str) -> String {
+    title
+        .chars()
+        .flat_map(|c| match c {
+            '*' | '_' | '`' | '[' | ']' | '<' | '>' | '\\' => vec!['\\', c],
+            _ => vec![c],
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{embed_in_body, load_template, render, strip_from_body, DEFAULT_COMMENT_TEMPLATE};
+    use crate::{
+        errors::StError,
+        github::status::{
+            CheckStatus, Mergeability, PullRequestState, PullRequestStatus, ReviewStatus,
+        },
+        tree::{RemoteMetadata, StackTree},
+    };
+    use std::{collections::HashMap, path::Path};
+
+    #[test]
+    fn renders_subtree_with_forks() {
+        let mut tree = StackTree::new("main".to_string());
+        for (parent, child) in [
+            ("main", "feat-a"),
+            ("feat-a", "feat-b"),
+            ("feat-a", "feat-c"),
+            ("feat-c", "feat-d"),
+            ("main", "other"),
+        ] {
+            tree.insert(parent, "oid", child).unwrap();
+        }
+        for (branch, number) in [("feat-a", 1), ("feat-b", 2), ("feat-c", 3), ("other", 4)] {
+            tree.get_mut(branch).unwrap().remote = Some(RemoteMetadata::new(number));
+        }
+
+        let status = |number: u64, title: &str, state, review| PullRequestStatus {
+            number,
+            title: title.to_string(),
+            body: String::new(),
+            state,
+            base_ref: String::new(),
+            head_ref: String::new(),
+            head_oid: String::new(),
+            review,
+            mergeable: Mergeability::Unknown,
+            checks: CheckStatus::None,
+            failing_checks: Vec::new(),
+            unresolved_threads: None,
+        };
+        let statuses = HashMap::from([
+            (
+                1,
+                status(1, "Add `a`", PullRequestState::Open, ReviewStatus::Approved),
+            ),
+            (
+                2,
+                status(2, "Add b", PullRequestState::Draft, ReviewStatus::None),
+            ),
+        ]);
+
+        let comment = render(
+            "{{branch}} (#{{pr}}) on {{trunk}}\n{{stack}}",
+            &tree,
+            "feat-b",
+            &statuses,
+            |_, _| None,
+        )
+        .unwrap();
+        assert_eq!(
+            comment,
+            "feat-b (#2) on main\n\
+             * `main`\n  \
+             * #1 Add \\`a\\` · 🟢 Open · ✅ Approved\n    \
+             * #2 Add b · 📝 Draft · No reviews 👈\n    \
+             * #3\n      \
+             * `feat-d` _(not submitted)_\n"
+        );
+
+        let comment = render(
+            DEFAULT_COMMENT_TEMPLATE,
+            &tree,
+            "feat-c",
+            &statuses,
+            |p, b| Some(format!("https://github.com/fork/repo/compare/{p}...{b}")),
+        )
+        .unwrap();
+        assert!(comment.contains("    * #3 👈\n"));
+        assert!(comment.contains(
+            "      * [`feat-d`](https://github.com/fork/repo/compare/feat-c...feat-d) \
+             _(dependent, opened once `feat-c` is merged)_\n"
+        ));
+        assert!(!comment.contains("#4"));
+    }
+
+    #[test]
+    fn embeds_overview_in_body() {
+        let section = |overview: &str| {
+            format!("<!-- st:stack-overview:start -->\n{overview}\n<!-- st:stack-overview:end -->")
+        };
+
+        // The section is appended after a blank line, whether or not the description ends with a
+        // newline, and stripping it restores the description without trailing whitespace.
+        for body in ["Fixes a bug.", "Fixes a bug.\n", "Fixes a bug.\r\n\n"] {
+            let embedded = embed_in_body(body, "v1");
+            assert_eq!(embedded, format!("Fixes a bug.\n\n{}", section("v1")));
+            let stripped = strip_from_body(&embedded).unwrap();
+            assert_eq!(stripped, "Fixes a bug.");
+            assert_eq!(embed_in_body(&stripped, "v1"), embedded);
+        }
+        let body = "Fixes a bug.\n";
+        assert_eq!(embed_in_body("", "v1"), section("v1"));
+        assert_eq!(strip_from_body(&section("v1")).as_deref(), Some(""));
+        assert_eq!(strip_from_body(body), None);
+
+        // Sections are replaced in place, leaving the author's text around them untouched.
+        let edited = format!("Intro\n{}\nOutro", section("v1"));
+        assert_eq!(
+            embed_in_body(&edited, "v2"),
+            format!("Intro\n{}\nOutro", section("v2"))
+        );
+        assert_eq!(strip_from_body(&edited).as_deref(), Some("Intro\n\nOutro"));
+    }
+
+    #[test]
+    fn loads_templates_within_the_repository_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("repo");
+        std::fs::create_dir_all(root.join(".st")).unwrap();
+        std::fs::write(root.join("comment.md"), "{{stack}}").unwrap();
+        std::fs::write(dir.path().join("secret"), "secret").unwrap();
+
+        assert_eq!(
+            load_template(&root, None).unwrap(),
+            DEFAULT_COMMENT_TEMPLATE
+        );
+        assert_eq!(
+            load_template(&root, Some(Path::new("comment.md"))).unwrap(),
+            "{{stack}}"
+        );
+        assert_eq!(
+            load_template(&root, Some(Path::new(".st/../comment.md"))).unwrap(),
+            "{{stack}}"
+        );
+
+        let secret = dir.path().join("secret");
+        for path in [Path::new("../secret"), secret.as_path()] {
+            assert!(
+                matches!(
+                    load_template(&root, Some(path)),
+                    Err(StError::CommentTemplateOutsideRepository(_))
+                ),
+                "{}",
+                path.display()
+            );
+        }
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(&secret, root.join(".st/stack_comment.md")).unwrap();
+            assert!(matches!(
+                load_template(&root, None),
+                Err(StError::CommentTemplateOutsideRepository(_))
+            ));
+        }
+    }
+}
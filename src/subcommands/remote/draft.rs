@@ -0,0 +1,366 @@
+//! `ready` and `draft` subcommands.
+
+use crate::{
+    config::StConfig,
+    ctx::StContext,
+    errors::{StError, StResult},
+    git::RepositoryExt,
+    github::GitHubClient,
+    tree::StackTree,
+};
+use clap::Args;
+use nu_ansi_term::Color;
+use octocrab::models::IssueState;
+
+/// Marks a pull request as ready for review.
+const MARK_READY_MUTATION: &str = "mutation($id: ID!) { markPullRequestReadyForReview(input: { pullRequestId: $id }) { pullRequest { isDraft } } }";
+
+/// Converts a pull request to a draft.
+const CONVERT_TO_DRAFT_MUTATION: &str = "mutation($id: ID!) { convertPullRequestToDraft(input: { pullRequestId: $id }) { pullRequest { isDraft } } }";
+
+/// CLI arguments for the `ready` subcommand.
+#[derive(Debug, Clone, Eq, PartialEq, Args)]
+pub struct ReadyCmd {
+    /// The branches to target.
+    #[clap(flatten)]
+    scope: DraftScopeArgs,
+    /// Mark only the lowest unmerged pull request as ready for review, and keep every pull
+    /// request above it in draft. Defaults to the `submit.draft_upstack` setting.
+    #[clap(long, overrides_with = "no_keep_upstack_draft")]
+    keep_upstack_draft: bool,
+    /// Mark every targeted pull request as ready for review, even if `submit.draft_upstack` is
+    /// set.
+    #[clap(long, overrides_with = "keep_upstack_draft")]
+    no_keep_upstack_draft: bool,
+}
+
+/// CLI arguments for the `draft` subcommand.
+#[derive(Debug, Clone, Eq, PartialEq, Args)]
+pub struct DraftCmd {
+    /// The branches to target.
+    #[clap(flatten)]
+    scope: DraftScopeArgs,
+}
+
+/// Selection of the branches whose pull requests to update. Defaults to the current branch.
+#[derive(Debug, Clone, Eq, PartialEq, Args)]
+pub struct DraftScopeArgs {
+    /// Target every branch of the current stack.
+    #[clap(long, conflicts_with = "downstack")]
+    stack: bool,
+    /// Target the current branch and the branches below it, down to trunk.
+    #[clap(long)]
+    downstack: bool,
+}
+
+impl ReadyCmd {
+    /// Run the `ready` subcommand.
+    pub async fn run(self, ctx: StContext<'_>) -> StResult<()> {
+        let branches = self.scope.resolve(&ctx)?;
+        set_draft_state(
+            &ctx,
+            &ctx.github_client()?,
+            &branches,
+            self.target(&ctx.cfg),
+        )
+        .await
+    }
+
+    /// Returns the draft state to move the pull requests to. The `submit.draft_upstack` setting
+    /// applies unless either flag is passed.
+    fn target(&self, cfg: &StConfig) -> DraftTarget {
+        let keep_upstack_draft = match (self.keep_upstack_draft, self.no_keep_upstack_draft) {
+            (true, _) => true,
+            (_, true) => false,
+            _ => cfg.submit.draft_upstack,
+        };
+        if keep_upstack_draft {
+            DraftTarget::LowestReady
+        } else {
+            DraftTarget::Ready
+        }
+    }
+}
+
+impl DraftCmd {
+    /// Run the `draft` subcommand.
+    pub async fn run(self, ctx: StContext<'_>) -> StResult<()> {
+        let branches = self.scope.resolve(&ctx)?;
+        set_draft_state(&ctx, &ctx.github_client()?, &branches, DraftTarget::Draft).await
+    }
+}
+
+impl DraftScopeArgs {
+    /// Resolves the targeted branches, from the bottom of the stack upwards.
+    fn resolve(&self, ctx: &StContext<'_>) -> StResult<Vec<String>> {
+        let current = ctx.repository.current_branch_name()?;
+        if self.stack {
+            ctx.discover_stack()
+        } else if self.downstack {
+            let mut downstack = ctx.tree.ancestors(&current)?;
+            downstack.push(current);
+            Ok(downstack)
+        } else {
+            Ok(vec![current])
+        }
+    }
+}
+
+/// Marks the lowest open pull request of each stack among `branches` as ready for review, and
+/// converts the pull requests above it to drafts, as configured by `submit.draft_upstack`.
+///
+/// ## Takes
+/// - `ctx` - The context of the repository.
+/// - `gh_client` - The client of the repository.
+/// - `branches` - The branches whose pull requests to update, parents before their children.
+pub async fn keep_upstack_draft(
+    ctx: &StContext<'_>,
+    gh_client: &GitHubClient,
+    branches: &[String],
+) -> StResult<()> {
+    set_draft_state(ctx, gh_client, branches, DraftTarget::LowestReady).await
+}
+
+/// The draft state to move pull requests to.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum DraftTarget {
+    /// Mark every pull request as ready for review.
+    Ready,
+    /// Convert every pull request to a draft.
+    Draft,
+    /// Mark the lowest open pull request as ready for review, and convert the others to drafts.
+    LowestReady,
+}
+
+/// Moves the open pull requests of the targeted branches to the requested draft state.
+async fn set_draft_state(
+    ctx: &StContext<'_>,
+    gh_client: &GitHubClient,
+    branches: &[String],
+    target: DraftTarget,
+) -> StResult<()> {
+    // The lowest unmerged pull request may be below the targeted branches.
+    let mut inspected = Vec::new();
+    if target == DraftTarget::LowestReady {
+        for branch in branches {
+            for ancestor in ctx.tree.ancestors(branch)? {
+                if !inspected.contains(&ancestor) && !branches.contains(&ancestor) {
+                    inspected.push(ancestor);
+                }
+            }
+        }
+    }
+    inspected.extend(branches.iter().cloned());
+
+    // Fetch the pull requests of the branches concurrently.
+    let numbers = inspected
+        .iter()
+        .filter_map(|b| ctx.tree.get(b).and_then(|b| b.remote.as_ref()))
+        .map(|r| r.pr_number)
+        .collect::<Vec<_>>();
+    let mut prs = gh_client.pull_requests(&numbers).await?.into_iter();
+
+    // Collect the open pull requests of the branches.
+    let mut open = Vec::new();
+    for branch in inspected.iter().filter(|b| !ctx.tree.is_trunk(b)) {
+        let in_scope = branches.contains(branch);
+        let tracked_branch = ctx
+            .tree
+            .get(branch)
+            .ok_or_else(|| StError::BranchNotTracked(branch.to_string()))?;
+        if tracked_branch.remote.is_none() {
+            if !in_scope {
+                continue;
+            }
+            println!(
+                "Branch `{}` has not been submitted. Skipping.",
+                Color::Green.paint(branch)
+            );
+            continue;
+        }
+
+        let pr = prs.next().ok_or(StError::PullRequestNotFound)?;
+        let is_open = pr.merged_at.is_none() && matches!(pr.state, Some(IssueState::Open));
+        if !is_open {
+            if !in_scope {
+                continue;
+            }
+            println!(
+                "Pull request #{} for branch `{}` is no longer open. Skipping.",
+                pr.number,
+                Color::Green.paint(branch)
+            );
+            continue;
+        }
+        open.push((branch.clone(), pr, in_scope));
+    }
+
+    let open_branches = open
+        .iter()
+        .map(|(branch, _, _)| branch.as_str())
+        .collect::<Vec<_>>();
+    for (branch, pr, in_scope) in open.iter() {
+        if !in_scope {
+            continue;
+        }
+        let draft = wants_draft(&ctx.tree, branch, &open_branches, target)?;
+        let state = if draft {
+            Color::Purple.paint("draft")
+        } else {
+            Color::Green.paint("ready for review")
+        };
+
+        if pr.draft.unwrap_or_default() == draft {
+            println!(
+                "Pull request #{} for branch `{}` is already {}.",
+                pr.number,
+                Color::Green.paint(branch),
+                state
+            );
+            continue;
+        }
+
+        let id = pr.node_id.as_deref().ok_or(StError::PullRequestNotFound)?;
+        let mutation = if draft {
+            CONVERT_TO_DRAFT_MUTATION
+        } else {
+            MARK_READY_MUTATION
+        };
+        gh_client
+            .graphql(mutation, serde_json::json!({ "id": id }))
+            .await?;
+        println!(
+            "Marked pull request #{} for branch `{}` as {}.",
+            pr.number,
+            Color::Green.paint(branch),
+            state
+        );
+    }
+
+    Ok(())
+}
+
+/// Returns whether the pull request of `branch` should be a draft.
+///
+/// ## Takes
+/// - `tree` - The tree of tracked branches.
+/// - `branch` - The branch of the pull request.
+/// - `open_branches` - The branches that have an open pull request.
+/// - `target` - The requested draft state.
+///
+/// ## Returns
+/// - `Result<bool>` - Whether the pull request should be a draft. With
+///   [DraftTarget::LowestReady], pull requests stacked on top of another open pull request are
+///   drafts, while forks of the same parent are treated alike.
+fn wants_draft(
+    tree: &StackTree,
+    branch: &str,
+    open_branches: &[&str],
+    target: DraftTarget,
+) -> StResult<bool> {
+    Ok(match target {
+        DraftTarget::Ready => false,
+        DraftTarget::Draft => true,
+        DraftTarget::LowestReady => tree
+            .ancestors(branch)?
+            .iter()
+            .any(|ancestor| open_branches.contains(&ancestor.as_str())),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{set_draft_state, wants_draft, DraftTarget, ReadyCmd, MARK_READY_MUTATION};
+    use crate::{
+        config::StConfig,
+        ctx::StContext,
+        github::{fixtures::pull, mock::MockGitHub},
+        tree::{RemoteMetadata, StackTree},
+    };
+    use clap::Parser;
+    use git2::Repository;
+    use serde_json::json;
+
+    /// Parses the arguments of the `ready` subcommand.
+    #[derive(Parser)]
+    struct Ready {
+        #[clap(flatten)]
+        cmd: ReadyCmd,
+    }
+
+    #[test]
+    fn keeps_pull_requests_above_open_ones_in_draft() {
+        // `b` and `c` are forks on top of `a`, and `d` is stacked on `c`.
+        let mut tree = StackTree::new("main".to_string());
+        for (parent, child) in [("main", "a"), ("a", "b"), ("a", "c"), ("c", "d")] {
+            tree.insert(parent, "oid", child).unwrap();
+        }
+        let drafts = |open: &[&str]| {
+            open.iter()
+                .map(|b| wants_draft(&tree, b, open, DraftTarget::LowestReady).unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(drafts(&["a", "b", "c", "d"]), [false, true, true, true]);
+
+        // Once `a` is merged, both of its forks are the lowest open pull requests.
+        assert_eq!(drafts(&["b", "c", "d"]), [false, false, true]);
+
+        for branch in ["a", "d"] {
+            assert!(!wants_draft(&tree, branch, &["a", "d"], DraftTarget::Ready).unwrap());
+            assert!(wants_draft(&tree, branch, &["a", "d"], DraftTarget::Draft).unwrap());
+        }
+    }
+
+    #[tokio::test]
+    async fn marks_upstack_pull_request_ready_despite_draft_upstack() {
+        let dir = tempfile::tempdir().unwrap();
+        let repository = Repository::init(dir.path()).unwrap();
+        let mut cfg = StConfig::default();
+        cfg.submit.draft_upstack = true;
+        let mut ctx = StContext::fresh(cfg, &repository, "main".to_string());
+        ctx.tree.insert("main", "oid", "a").unwrap();
+        ctx.tree.insert("a", "oid", "b").unwrap();
+        ctx.tree.get_mut("a").unwrap().remote = Some(RemoteMetadata::new(1));
+        ctx.tree.get_mut("b").unwrap().remote = Some(RemoteMetadata::new(2));
+
+        // `a` is open and ready for review, and `b` is an open draft on top of it.
+        let mock = MockGitHub::start(|request| match request.path.as_str() {
+            "/repos/owner/repo/pulls/1" => (200, pull(1, "a")),
+            "/repos/owner/repo/pulls/2" => {
+                let mut pr = pull(2, "b");
+                pr["draft"] = json!(true);
+                (200, pr)
+            }
+            _ => (200, json!({ "data": {} })),
+        })
+        .await;
+        let target = |args: &[&str]| {
+            let args = ["ready"].iter().chain(args);
+            Ready::try_parse_from(args).unwrap().cmd.target(&ctx.cfg)
+        };
+        let branches = ["b".to_string()];
+
+        // The setting keeps `b` in draft by default.
+        assert_eq!(target(&[]), DraftTarget::LowestReady);
+        set_draft_state(&ctx, &mock.client(), &branches, target(&[]))
+            .await
+            .unwrap();
+        assert!(mock.requests().iter().all(|r| r.path != "/graphql"));
+
+        // The flag overrides the setting, and marks `b` as ready for review.
+        let no_keep = target(&["--no-keep-upstack-draft"]);
+        assert_eq!(no_keep, DraftTarget::Ready);
+        assert_eq!(
+            target(&["--no-keep-upstack-draft", "--keep-upstack-draft"]),
+            DraftTarget::LowestReady
+        );
+        set_draft_state(&ctx, &mock.client(), &branches, no_keep)
+            .await
+            .unwrap();
+        let requests = mock.requests();
+        let mutation = requests.iter().find(|r| r.path == "/graphql").unwrap();
+        assert_eq!(mutation.body["query"], MARK_READY_MUTATION);
+        assert_eq!(mutation.body["variables"]["id"], "PR_2");
+    }
+}
@@ -0,0 +1,401 @@
+//! `merge` subcommand.
+
+use super::draft;
+use crate::{
+    config::MergeMethod,
+    ctx::StContext,
+    errors::{StError, StResult},
+    git::{GitCommandError, RepositoryExt},
+    github::{GitHubClient, PullRequestState},
+};
+use clap::Args;
+use git2::BranchType;
+use nu_ansi_term::Color;
+use std::time::Duration;
+
+/// How often GitHub is polled while waiting for a merge or for checks.
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How long to wait for GitHub to report a pull request as merged.
+const MERGE_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// How long to wait for the checks of a pull request to settle, once the branches above a landed
+/// pull request were pushed.
+const CHECKS_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+/// How long a pushed pull request without checks is assumed to be waiting for its checks to be
+/// queued.
+const CHECKS_GRACE_PERIOD: Duration = Duration::from_secs(60);
+
+/// CLI arguments for the `merge` subcommand.
+#[derive(Debug, Clone, Eq, PartialEq, Args)]
+pub struct MergeCmd {
+    /// Merge every pull request of the current stack, bottom to top, waiting for the checks of
+    /// each one to pass after the previous one was merged.
+    #[clap(long, short)]
+    all: bool,
+    /// How to merge the pull requests. Defaults to the `merge.method` setting.
+    #[clap(long, short, value_enum)]
+    method: Option<MergeMethod>,
+}
+
+impl MergeCmd {
+    /// Run the `merge` subcommand.
+    pub async fn run(self, mut ctx: StContext<'_>) -> StResult<()> {
+        let gh_client = ctx.github_client()?;
+        let method = self.method.unwrap_or(ctx.cfg.merge.method);
+
+        // The stack must be restacked, so that the commits of each branch can be told apart from
+        // the commits of its parent once the parent is merged.
+        let current_branch = ctx.repository.current_branch_name()?;
+        let stack = ctx.discover_stack()?;
+        ctx.check_cleanliness(&stack)?;
+
+        let trunk = stack[0].clone();
+        if stack.len() < 2 {
+            return Err(StError::NothingToMerge);
+        }
+
+        // Pull requests that were already merged are landed along the way, until the lowest open
+        // pull request of the stack is merged, or every one of them with `--all`.
+        let mut pushed = false;
+        for branch in stack[1..].iter() {
+            let number = ctx
+                .tree
+                .get(branch)
+                .and_then(|b| b.remote.as_ref())
+                .map(|r| r.pr_number)
+                .ok_or(StError::NothingToMerge)?;
+
+            let status = ctx
+                .refresh_pull_requests(&gh_client, std::slice::from_ref(branch))
+                .await?
+                .remove(branch)
+                .ok_or(StError::PullRequestNotFound)?;
+            let merged = match status.state {
+                PullRequestState::Merged => {
+                    println!(
+                        "Pull request #{} for branch `{}` is already merged.",
+                        number,
+                        Color::Green.paint(branch)
+                    );
+                    false
+                }
+                PullRequestState::Closed => return Err(StError::PullRequestClosed(number)),
+                PullRequestState::Open | PullRequestState::Draft => {
+                    // Merge the local head of the branch, so that changes that were not pulled
+                    // are never merged unseen.
+                    let head_sha = ctx
+                        .repository
+                        .find_branch(branch, BranchType::Local)?
+                        .get()
+                        .target()
+                        .ok_or(StError::BranchUnavailable)?
+                        .to_string();
+
+                    // Branches above a landed pull request were just retargeted and pushed, so
+                    // their checks are given time to run again.
+                    if pushed {
+                        println!(
+                            "\n⏳ Waiting for the checks of pull request #{} to pass...",
+                            number
+                        );
+                        gh_client
+                            .wait_for_checks(
+                                number,
+                                &head_sha,
+                                POLL_INTERVAL,
+                                CHECKS_GRACE_PERIOD,
+                                CHECKS_TIMEOUT,
+                            )
+                            .await?;
+                    }
+
+                    println!(
+                        "\n🔀 Merging pull request #{} for branch `{}` into `{}`...",
+                        number,
+                        Color::Green.paint(branch),
+                        Color::Yellow.paint(&status.base_ref)
+                    );
+                    gh_client
+                        .merge_pull_request(number, method, &head_sha)
+                        .await?;
+                    gh_client
+                        .wait_for_merge(number, POLL_INTERVAL, MERGE_TIMEOUT)
+                        .await?;
+                    true
+                }
+            };
+
+            self.land(&mut ctx, &gh_client, branch, &trunk).await?;
+            pushed = true;
+
+            // The next pull request is now the lowest of the stack, and must be ready for review
+            // before it can be merged.
+            if ctx.cfg.submit.draft_upstack {
+                let remaining = stack
+                    .iter()
+                    .filter(|b| !ctx.tree.is_trunk(b) && ctx.tree.get(b).is_some())
+                    .cloned()
+                    .collect::<Vec<_>>();
+                draft::keep_upstack_draft(&ctx, &gh_client, &remaining).await?;
+            }
+
+            if merged && !self.all {
+                break;
+            }
+        }
+
+        // Return to the branch the command was run from, unless it was merged.
+        let checkout = if ctx.tree.get(&current_branch).is_some() {
+            current_branch
+        } else {
+            trunk
+        };
+        ctx.repository.checkout_branch(&checkout)?;
+
+        println!("\n🎉 Merge completed");
+        Ok(())
+    }
+
+    /// Lands a merged branch: updates the trunk, deletes the branch, restacks the branches above it
+    /// onto the trunk and retargets their pull requests.
+    ///
+    /// ## Takes
+    /// - `ctx` - The context of the repository.
+    /// - `gh_client` - The client of the repository.
+    /// - `merged` - The branch whose pull request was merged.
+    /// - `trunk` - The trunk branch that the pull request was merged into.
+    ///
+    /// ## Returns
+    /// - `Result<()>` - Nothing, or the first error.
+    async fn land(
+        &self,
+        ctx: &mut StContext<'_>,
+        gh_client: &GitHubClient,
+        merged: &str,
+        trunk: &str,
+    ) -> StResult<()> {
+        let merged_branch = ctx
+            .tree
+            .get(merged)
+            .ok_or_else(|| StError::BranchNotTracked(merged.to_string()))?;
+        let children = merged_branch.children.iter().cloned().collect::<Vec<_>>();
+        let upstack = children
+            .iter()
+            .map(|child| ctx.tree.subtree(child))
+            .collect::<StResult<Vec<_>>>()?
+            .concat();
+
+        // Branches checked out in other worktrees can be neither deleted nor rebased from here.
+        for branch in [trunk, merged]
+            .into_iter()
+            .chain(upstack.iter().map(String::as_str))
+        {
+            if let Some(path) = ctx.repository.checked_out_elsewhere(branch)? {
+                return Err(StError::BranchCheckedOutInWorktree(
+                    branch.to_string(),
+                    path,
+                ));
+            }
+        }
+
+        // Pull the merge into the trunk.
+        println!(
+            "\n🐙 Pulling `{}` from remote `{}`...",
+            Color::Yellow.paint(trunk),
+            Color::Blue.paint(ctx.remote_name())
+        );
+        ctx.repository.pull_branch(trunk, ctx.remote_name())?;
+
+        // Delete the merged branch. Its children are moved onto the trunk, and keep the head of the
+        // merged branch as the commit they were based on.
+        ctx.repository
+            .find_branch(merged, BranchType::Local)?
+            .delete()?;
+        ctx.tree.delete(merged)?;
+        println!("Deleted local branch `{}`.", Color::Green.paint(merged));
+
+        // Restack the branches above the merged branch. Only the commits of each branch are
+        // replayed, as the commits of the merged branch may have been squashed or rebased.
+        println!(
+            "\n🧙 Restacking branches onto `{}`...",
+            Color::Yellow.paint(trunk)
+        );
+        for branch in upstack.iter() {
+            let tracked_branch = ctx
+                .tree
+                .get(branch)
+                .ok_or_else(|| StError::BranchNotTracked(branch.clone()))?;
+            let parent = tracked_branch.parent.clone().expect("Parent must exist");
+            let upstream = tracked_branch
+                .parent_oid_cache
+                .clone()
+                .ok_or(StError::MissingParentOidCache)?;
+
+            if let Err(e) = ctx
+                .repository
+                .rebase_branch_range_onto(branch, &parent, &upstream)
+            {
+                ctx.repository.abort_rebase()?;
+                eprintln!(
+                    "Failed to restack branch `{}` onto `{}`.",
+                    Color::Green.paint(branch),
+                    Color::Yellow.paint(&parent)
+                );
+                return Err(e.into());
+            }
+
+            let parent_oid = ctx
+                .repository
+                .find_branch(&parent, BranchType::Local)?
+                .get()
+                .target()
+                .ok_or(StError::MissingParentOidCache)?;
+            ctx.tree
+                .get_mut(branch)
+                .ok_or_else(|| StError::BranchNotTracked(branch.clone()))?
+                .parent_oid_cache = Some(parent_oid.to_string());
+            println!(
+                "Restacked branch `{}` onto `{}`.",
+                Color::Green.paint(branch),
+                Color::Yellow.paint(&parent)
+            );
+        }
+
+        // Push the restacked branches that have pull requests.
+        for branch in upstack.iter() {
+            if ctx
+                .tree
+                .get(branch)
+                .and_then(|b| b.remote.as_ref())
+                .is_some()
+            {
+                ctx.repository
+                    .push_branch(branch, ctx.push_remote_name(), true)?;
+                println!("Pushed branch `{}`.", Color::Green.paint(branch));
+            }
+        }
+
+        // Retarget the pull requests of the children before deleting the remote branch, as GitHub
+        // closes pull requests whose base branch is deleted.
+        for child in children.iter() {
+            if let Some(remote) = ctx.tree.get(child).and_then(|b| b.remote.as_ref()) {
+                gh_client
+                    .retarget_pull_request(remote.pr_number, trunk)
+                    .await?;
+                println!(
+                    "Retargeted pull request #{} onto `{}`.",
+                    remote.pr_number,
+                    Color::Yellow.paint(trunk)
+                );
+            }
+        }
+
+        // GitHub may already have deleted the branch, if the repository is set up to do so.
+        let refspec = format!(":refs/heads/{}", merged);
+        match ctx
+            .repository
+            .push_refspec(&refspec, ctx.push_remote_name())
+        {
+            Ok(()) => println!("Deleted remote branch `{}`.", Color::Green.paint(merged)),
+            Err(GitCommandError::Command(e)) if e.contains("remote ref does not exist") => {
+                println!(
+                    "Remote branch `{}` was already deleted.",
+                    Color::Green.paint(merged)
+                )
+            }
+            Err(e) => return Err(e.into()),
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::MergeCmd;
+    use crate::{
+        config::StConfig,
+        ctx::StContext,
+        github::{
+            fixtures::{git, pull},
+            mock::MockGitHub,
+        },
+        tree::RemoteMetadata,
+    };
+    use git2::Repository;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn lands_squash_merged_branch() {
+        let dir = tempfile::tempdir().unwrap();
+        let (remote, work, other) = (
+            dir.path().join("remote.git"),
+            dir.path().join("work"),
+            dir.path().join("other"),
+        );
+        git(dir.path(), &["init", "--bare", "-b", "main", "remote.git"]);
+        git(dir.path(), &["clone", "remote.git", "work"]);
+        git(&work, &["config", "user.name", "st"]);
+        git(&work, &["config", "user.email", "st@example.com"]);
+
+        // Stack `b` on top of `a` on top of `main`, and push everything.
+        git(&work, &["checkout", "-b", "main"]);
+        git(&work, &["commit", "--allow-empty", "-m", "base"]);
+        git(&work, &["checkout", "-b", "a"]);
+        git(&work, &["commit", "--allow-empty", "-m", "a1"]);
+        std::fs::write(work.join("a.txt"), "a").unwrap();
+        git(&work, &["add", "a.txt"]);
+        git(&work, &["commit", "-m", "a2"]);
+        git(&work, &["checkout", "-b", "b"]);
+        std::fs::write(work.join("a.txt"), "ab").unwrap();
+        git(&work, &["commit", "-am", "b1"]);
+        git(&work, &["push", "origin", "main", "a", "b"]);
+
+        // Squash-merge `a` into `main` from another clone, as GitHub would.
+        git(dir.path(), &["clone", "remote.git", "other"]);
+        git(&other, &["merge", "--squash", "origin/a"]);
+        git(&other, &["commit", "-m", "a (#1)"]);
+        git(&other, &["push", "origin", "main"]);
+
+        let repository = Repository::open(&work).unwrap();
+        let mut ctx = StContext::fresh(StConfig::default(), &repository, "main".to_string());
+        let main_oid = git(&work, &["rev-parse", "main"]);
+        let a_oid = git(&work, &["rev-parse", "a"]);
+        ctx.tree.insert("main", &main_oid, "a").unwrap();
+        ctx.tree.insert("a", &a_oid, "b").unwrap();
+        ctx.tree.get_mut("a").unwrap().remote = Some(RemoteMetadata::new(1));
+        ctx.tree.get_mut("b").unwrap().remote = Some(RemoteMetadata::new(2));
+
+        let mock = MockGitHub::start(|request| match request.method.as_str() {
+            "PATCH" => (200, pull(2, "b")),
+            _ => (404, json!({ "message": "Not Found" })),
+        })
+        .await;
+        let cmd = MergeCmd {
+            all: false,
+            method: None,
+        };
+        cmd.land(&mut ctx, &mock.client(), "a", "main")
+            .await
+            .unwrap();
+
+        // `b` now holds only its own commit on top of the squashed `a`, locally and remotely.
+        assert!(ctx.tree.get("a").is_none());
+        assert_eq!(ctx.tree.get("b").unwrap().parent.as_deref(), Some("main"));
+        assert_eq!(git(&work, &["log", "--format=%s", "main..b"]), "b1");
+        assert_eq!(git(&work, &["log", "-1", "--format=%s", "main"]), "a (#1)");
+        assert_eq!(
+            git(&remote, &["rev-parse", "b"]),
+            git(&work, &["rev-parse", "b"])
+        );
+        assert!(git(&remote, &["branch", "--list", "a"]).is_empty());
+        assert!(git(&work, &["branch", "--list", "a"]).is_empty());
+
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].path, "/repos/owner/repo/pulls/2");
+        assert_eq!(requests[0].body["base"], "main");
+    }
+}
@@ -0,0 +1,58 @@
+//! `metadata` subcommand.
+
+use crate::{ctx::StContext, errors::StResult};
+use clap::{Args, Subcommand};
+use nu_ansi_term::Color;
+
+/// CLI arguments for the `metadata` subcommand.
+#[derive(Debug, Clone, Eq, PartialEq, Args)]
+pub struct MetadataCmd {
+    /// The metadata operation to perform.
+    #[clap(subcommand)]
+    action: MetadataAction,
+}
+
+/// Operations on the shared stack metadata.
+#[derive(Debug, Clone, Eq, PartialEq, Subcommand)]
+pub enum MetadataAction {
+    /// Push the local stack metadata to the remote.
+    Push,
+    /// Pull the stack metadata from the remote, and merge it into the local stack tree.
+    Pull,
+}
+
+impl MetadataCmd {
+    /// Run the `metadata` subcommand.
+    pub fn run(self, mut ctx: StContext<'_>) -> StResult<()> {
+        let remote = ctx.remote_name().to_string();
+
+        match self.action {
+            MetadataAction::Push => {
+                println!(
+                    "🐙 Pushing stack metadata to remote `{}`...",
+                    Color::Blue.paint(&remote)
+                );
+                ctx.push_metadata(&remote)?;
+                println!("\n📚 Stack metadata pushed.");
+            }
+            MetadataAction::Pull => {
+                println!(
+                    "🐙 Pulling stack metadata from remote `{}`...",
+                    Color::Blue.paint(&remote)
+                );
+                if !ctx.pull_metadata(&remote)? {
+                    println!(
+                        "\nRemote `{}` has no stack metadata. Share it first with `{}`.",
+                        Color::Blue.paint(&remote),
+                        Color::Blue.paint("st metadata push")
+                    );
+                    return Ok(());
+                }
+
+                println!("\n📚 Stack metadata merged.\n");
+                ctx.print_tree()?;
+            }
+        }
+        Ok(())
+    }
+}
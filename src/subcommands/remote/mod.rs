@@ -3,8 +3,23 @@
 mod submit;
 pub use submit::SubmitCmd;
 
+mod comment;
+
+mod overrides;
+
+mod template;
+
 mod sync;
 pub use sync::SyncCmd;
 
 mod status;
 pub use status::StatusCmd;
+
+mod merge;
+pub use merge::MergeCmd;
+
+mod metadata;
+pub use metadata::MetadataCmd;
+
+mod draft;
+pub use draft::{DraftCmd, ReadyCmd};
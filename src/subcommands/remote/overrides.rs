@@ -0,0 +1,184 @@
+//! Metadata of new pull requests provided up front, on the command line or in a file.
+
+use crate::errors::{StError, StResult};
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, Read},
+    path::Path,
+};
+
+/// Metadata of a new pull request. Unset fields are computed from the branch, or prompted for.
+#[derive(Default, Debug, Clone, Eq, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PullRequestFields {
+    /// The title of the pull request.
+    pub title: Option<String>,
+    /// The description of the pull request.
+    pub body: Option<String>,
+    /// Whether the pull request is opened as a draft.
+    pub draft: Option<bool>,
+    /// Users, or `org/team` teams, to request reviews from.
+    pub reviewers: Option<Vec<String>>,
+    /// Labels to add to the pull request.
+    pub labels: Option<Vec<String>>,
+    /// Users to assign to the pull request.
+    pub assignees: Option<Vec<String>>,
+}
+
+impl PullRequestFields {
+    /// Returns these fields, with every field that is set in `overrides` replaced.
+    ///
+    /// ## Takes
+    /// - `overrides` - The fields that take precedence, e.g. those of a single branch.
+    ///
+    /// ## Returns
+    /// - `PullRequestFields` - The merged fields.
+    pub fn overridden_by(&self, overrides: Option<&Self>) -> Self {
+        let Some(overrides) = overrides else {
+            return self.clone();
+        };
+        Self {
+            title: overrides.title.clone().or_else(|| self.title.clone()),
+            body: overrides.body.clone().or_else(|| self.body.clone()),
+            draft: overrides.draft.or(self.draft),
+            reviewers: overrides
+                .reviewers
+                .clone()
+                .or_else(|| self.reviewers.clone()),
+            labels: overrides.labels.clone().or_else(|| self.labels.clone()),
+            assignees: overrides
+                .assignees
+                .clone()
+                .or_else(|| self.assignees.clone()),
+        }
+    }
+
+    /// Returns these fields, with the reviewers, labels and assignees of `other` added to them.
+    /// Values already present, compared case-insensitively, are not added twice.
+    ///
+    /// ## Takes
+    /// - `other` - The fields whose participants to add.
+    ///
+    /// ## Returns
+    /// - `PullRequestFields` - The fields with the combined participants.
+    pub fn with_participants_of(&self, other: &Self) -> Self {
+        let union = |ours: &Option<Vec<String>>, theirs: &Option<Vec<String>>| {
+            let mut values = ours.clone().unwrap_or_default();
+            for value in theirs.iter().flatten() {
+                if !values.iter().any(|v| v.eq_ignore_ascii_case(value)) {
+                    values.push(value.clone());
+                }
+            }
+            (!values.is_empty()).then_some(values)
+        };
+        Self {
+            reviewers: union(&self.reviewers, &other.reviewers),
+            labels: union(&self.labels, &other.labels),
+            assignees: union(&self.assignees, &other.assignees),
+            ..self.clone()
+        }
+    }
+
+    /// Loads per-branch metadata from a TOML or JSON file, mapping branch names to
+    /// [PullRequestFields]. Files with a `.json` extension are parsed as JSON, others as TOML.
+    ///
+    /// ## Takes
+    /// - `path` - The path of the file.
+    ///
+    /// ## Returns
+    /// - `Result<BTreeMap<String, PullRequestFields>>` - The metadata of each branch, or an error.
+    pub fn load_overrides(path: &Path) -> StResult<BTreeMap<String, Self>> {
+        let contents = fs::read_to_string(path)?;
+        let is_json = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+
+        let overrides = if is_json {
+            serde_json::from_str(&contents).map_err(|e| e.to_string())
+        } else {
+            toml::from_str(&contents).map_err(|e| e.to_string())
+        };
+        overrides.map_err(|e| StError::InvalidMetadataFile(path.to_path_buf(), e))
+    }
+}
+
+/// Reads a pull request description from a file, or from stdin if `path` is `-`.
+///
+/// ## Takes
+/// - `path` - The path of the file.
+///
+/// ## Returns
+/// - `Result<String>` - The description, or an error.
+pub fn read_body_file(path: &Path) -> StResult<String> {
+    if path == Path::new("-") {
+        let mut body = String::new();
+        io::stdin().read_to_string(&mut body)?;
+        return Ok(body);
+    }
+    Ok(fs::read_to_string(path)?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::PullRequestFields;
+    use std::fs;
+
+    #[test]
+    fn per_branch_overrides_take_precedence() {
+        let dir = tempfile::tempdir().unwrap();
+        let toml_path = dir.path().join("prs.toml");
+        fs::write(
+            &toml_path,
+            "[feat-a]\ntitle = \"Feature A\"\ndraft = false\nlabels = []\n",
+        )
+        .unwrap();
+        let json_path = dir.path().join("prs.json");
+        fs::write(
+            &json_path,
+            r#"{"feat-a": {"title": "Feature A", "draft": false, "labels": []}}"#,
+        )
+        .unwrap();
+
+        let toml = PullRequestFields::load_overrides(&toml_path).unwrap();
+        let json = PullRequestFields::load_overrides(&json_path).unwrap();
+        assert_eq!(toml, json);
+
+        let cli = PullRequestFields {
+            title: Some("Shared".to_string()),
+            draft: Some(true),
+            labels: Some(vec!["stacked".to_string()]),
+            reviewers: Some(vec!["octocat".to_string()]),
+            ..Default::default()
+        };
+        let merged = cli.overridden_by(toml.get("feat-a"));
+        assert_eq!(merged.title.as_deref(), Some("Feature A"));
+        assert_eq!(merged.draft, Some(false));
+        assert_eq!(merged.labels, Some(vec![]));
+        assert_eq!(merged.reviewers, Some(vec!["octocat".to_string()]));
+        assert_eq!(cli.overridden_by(toml.get("feat-b")), cli);
+
+        let required = PullRequestFields {
+            reviewers: Some(vec!["OctoCat".to_string(), "org/core".to_string()]),
+            assignees: Some(vec!["hubot".to_string()]),
+            ..Default::default()
+        };
+        let combined = merged.with_participants_of(&required);
+        assert_eq!(
+            combined.reviewers,
+            Some(vec!["octocat".to_string(), "org/core".to_string()])
+        );
+        assert_eq!(combined.labels, None);
+        assert_eq!(combined.assignees, Some(vec!["hubot".to_string()]));
+        assert_eq!(combined.title, merged.title);
+    }
+
+    #[test]
+    fn rejects_unknown_fields() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("prs.toml");
+        fs::write(&path, "[feat-a]\nreviewer = \"octocat\"\n").unwrap();
+        assert!(PullRequestFields::load_overrides(&path).is_err());
+    }
+}
@@ -3,34 +3,150 @@
 use crate::{
     ctx::StContext,
     errors::{StError, StResult},
+    github::{status::CheckStatus, PullRequestState},
+    tree::{unix_timestamp, CachedPullRequest},
 };
 use clap::Args;
 use cli_table::{Cell, Style, Table};
-use octocrab::{models::IssueState, Octocrab};
+use git2::BranchType;
+use nu_ansi_term::Color;
+use std::time::{Duration, Instant};
+
+/// How often `status --watch` refreshes the pull requests.
+const WATCH_INTERVAL: Duration = Duration::from_secs(15);
+
+/// How long `status --watch` waits for the checks to settle.
+const WATCH_TIMEOUT: Duration = Duration::from_secs(60 * 60);
 
 /// CLI arguments for the `status` subcommand.
 #[derive(Debug, Clone, Eq, PartialEq, Args)]
-pub struct StatusCmd;
+pub struct StatusCmd {
+    /// Show the last-fetched status of the pull requests, without contacting GitHub.
+    #[clap(long, conflicts_with = "max_age")]
+    offline: bool,
+    /// Only refresh pull requests whose last-fetched status is older than this, e.g. `90s`, `5m`,
+    /// `2h` or `1d`.
+    #[clap(long, value_parser = parse_max_age)]
+    max_age: Option<u64>,
+    /// Keep refreshing the status until the checks of every open pull request have settled, for up
+    /// to an hour.
+    #[clap(long, short, conflicts_with_all = ["offline", "max_age"])]
+    watch: bool,
+}
 
 impl StatusCmd {
     /// Run the `status` subcommand.
-    pub async fn run(self, ctx: StContext<'_>) -> StResult<()> {
-        // Establish the GitHub API client.
-        let gh_client = Octocrab::builder()
-            .personal_token(ctx.cfg.github_token.clone())
-            .build()?;
-        let (owner, repo) = ctx.owner_and_repository()?;
-        let pulls = gh_client.pulls(&owner, &repo);
-
+    pub async fn run(self, mut ctx: StContext<'_>) -> StResult<()> {
         let current_stack = ctx.discover_stack()?;
+        if !self.watch {
+            println!("{}", self.refresh(&mut ctx, &current_stack).await?);
+            return Ok(());
+        }
 
+        // Stop watching on Ctrl+C, so that the refreshed status is persisted when the context is
+        // dropped.
+        tokio::select! {
+            result = self.watch(&mut ctx, &current_stack) => result,
+            _ = tokio::signal::ctrl_c() => {
+                println!("Stopped watching.");
+                Ok(())
+            }
+        }
+    }
+
+    /// Refreshes the status of the stack until the checks of every open pull request have
+    /// settled, redrawing the table in place.
+    ///
+    /// ## Takes
+    /// - `ctx` - The context of the repository.
+    /// - `stack` - The branches of the stack.
+    ///
+    /// ## Returns
+    /// - `Result<()>` - Nothing once the checks have settled, or an error after [WATCH_TIMEOUT].
+    async fn watch(&self, ctx: &mut StContext<'_>, stack: &[String]) -> StResult<()> {
+        let start = Instant::now();
+        loop {
+            let table = self.refresh(ctx, stack).await?;
+            print!("\x1B[2J\x1B[1;1H");
+            println!("{}", table);
+            let pending = stack.iter().any(|b| {
+                ctx.tree
+                    .get(b)
+                    .and_then(|b| b.remote.as_ref()?.cache.as_ref())
+                    .is_some_and(|c| is_open(c) && c.checks == CheckStatus::Pending)
+            });
+            if !pending {
+                println!("All checks have settled.");
+                return Ok(());
+            }
+            if start.elapsed() >= WATCH_TIMEOUT {
+                return Err(StError::WatchTimedOut(WATCH_TIMEOUT.as_secs() / 60));
+            }
+            println!(
+                "⏳ Waiting for checks to settle, refreshing every {}s. Press {} to stop.",
+                WATCH_INTERVAL.as_secs(),
+                Color::Blue.paint("Ctrl+C")
+            );
+            tokio::time::sleep(WATCH_INTERVAL).await;
+        }
+    }
+
+    /// Refreshes the pull requests of the stack whose cached status is missing or too old, and
+    /// renders the status table.
+    ///
+    /// ## Takes
+    /// - `ctx` - The context of the repository.
+    /// - `stack` - The branches of the stack.
+    ///
+    /// ## Returns
+    /// - `Result<String>` - The rendered table, or an error.
+    async fn refresh(&self, ctx: &mut StContext<'_>, stack: &[String]) -> StResult<String> {
+        let now = unix_timestamp();
+        let stale = stack
+            .iter()
+            .filter(|b| {
+                ctx.tree
+                    .get(b)
+                    .and_then(|b| b.remote.as_ref())
+                    .is_some_and(|r| match (&r.cache, self.max_age) {
+                        (Some(cache), Some(max_age)) => !cache.is_fresh(now, max_age),
+                        _ => true,
+                    })
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        if !self.offline && !stale.is_empty() {
+            let gh_client = ctx.github_client()?;
+            ctx.refresh_pull_requests(&gh_client, &stale).await?;
+        }
+
+        self.render(ctx, stack, &stale, now)
+    }
+
+    /// Renders the status table of the stack from the cached pull request states.
+    ///
+    /// ## Takes
+    /// - `ctx` - The context of the repository.
+    /// - `stack` - The branches of the stack.
+    /// - `refreshed` - The branches whose pull request was refreshed by this invocation.
+    /// - `now` - The current time, in seconds since the Unix epoch.
+    ///
+    /// ## Returns
+    /// - `Result<String>` - The rendered table, or an error.
+    fn render(
+        &self,
+        ctx: &StContext<'_>,
+        stack: &[String],
+        refreshed: &[String],
+        now: u64,
+    ) -> StResult<String> {
         let mut rows = vec![];
-        for branch in current_stack.into_iter() {
+        for branch in stack.iter() {
             let tracked_branch = ctx
                 .tree
-                .get(&branch)
+                .get(branch)
                 .ok_or_else(|| StError::BranchNotTracked(branch.clone()))?;
-            let mut row = Vec::with_capacity(4);
+            let mut row = Vec::with_capacity(9);
 
             row.push(branch.clone());
             row.push(
@@ -39,33 +155,69 @@ impl StatusCmd {
                     .clone()
                     .unwrap_or("n/a: trunk branch".to_string()),
             );
-            row.push(if ctx.needs_restack(&branch)? {
+            row.push(if ctx.needs_restack(branch)? {
                 "🔴 Needs Restack".to_string()
             } else {
                 "✅ Restacked".to_string()
             });
 
-            if let Some(remote) = &tracked_branch.remote {
-                let pr_info = pulls.get(remote.pr_number).await?;
-                let is_draft = pr_info.draft.unwrap_or_default();
-                let is_merged = pr_info.merged_at.is_some();
-                let is_closed = pr_info
-                    .state
-                    .map_or(true, |s| matches!(s, IssueState::Closed));
-
-                if is_draft {
-                    row.push("📝 Draft".to_string());
-                } else if is_merged {
-                    row.push("✅ Merged".to_string());
-                } else if is_closed {
-                    row.push("❌ Closed".to_string());
-                } else {
-                    row.push("🔍 In Review".to_string());
+            let cache = match tracked_branch.remote.as_ref().map(|r| r.cache.as_ref()) {
+                Some(Some(cache)) => cache,
+                missing => {
+                    row.push(match missing {
+                        Some(_) => "❔ Not Fetched".to_string(),
+                        None => "🚧 Not Submitted".to_string(),
+                    });
+                    row.resize(9, String::new());
+                    rows.push(row);
+                    continue;
                 }
+            };
+
+            let state = match cache.state {
+                PullRequestState::Draft => "📝 Draft",
+                PullRequestState::Merged => "✅ Merged",
+                PullRequestState::Closed => "❌ Closed",
+                PullRequestState::Open => "🔍 In Review",
+            };
+            // Show the age of statuses that were not refreshed by this invocation.
+            if self.offline || !refreshed.contains(branch) {
+                row.push(format!("{} ({})", state, cache.age(now)));
+            } else {
+                row.push(state.to_string());
+            }
+
+            // Checks, reviews and mergeability only matter while the pull request is open.
+            if is_open(cache) {
+                row.push(match cache.checks {
+                    CheckStatus::Failing if !cache.failing_checks.is_empty() => {
+                        format!("{}: {}", cache.checks, cache.failing_checks.join(", "))
+                    }
+                    checks => checks.to_string(),
+                });
+                row.push(cache.review.to_string());
+                row.push(match cache.unresolved_threads {
+                    Some(0) => "✅ All resolved".to_string(),
+                    Some(count) => format!("💬 {} unresolved", count),
+                    None => "❔ Unknown".to_string(),
+                });
+                row.push(cache.mergeable.to_string());
             } else {
-                row.push("🚧 Not Submitted".to_string());
+                row.resize(8, String::new());
             }
 
+            let local_oid = ctx
+                .repository
+                .find_branch(branch, BranchType::Local)?
+                .get()
+                .target()
+                .ok_or(StError::BranchUnavailable)?;
+            row.push(if local_oid.to_string() == cache.head_sha {
+                "✅ In Sync".to_string()
+            } else {
+                "⚠️ Differs".to_string()
+            });
+
             rows.push(row);
         }
 
@@ -76,9 +228,65 @@ impl StatusCmd {
                 "Parent Branch".cell().bold(true),
                 "Stack Status".cell().bold(true),
                 "PR Status".cell().bold(true),
+                "Checks".cell().bold(true),
+                "Review".cell().bold(true),
+                "Conversations".cell().bold(true),
+                "Mergeable".cell().bold(true),
+                "Remote Head".cell().bold(true),
             ])
             .bold(true);
-        println!("{}", table.display().expect("Failed to display table"));
-        Ok(())
+        Ok(table
+            .display()
+            .expect("Failed to display table")
+            .to_string())
+    }
+}
+
+/// Returns whether the cached pull request is still open.
+fn is_open(cache: &CachedPullRequest) -> bool {
+    matches!(
+        cache.state,
+        PullRequestState::Open | PullRequestState::Draft
+    )
+}
+
+/// Parses a duration such as `90`, `90s`, `5m`, `2h` or `1d` into seconds.
+fn parse_max_age(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let (digits, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => value.split_at(i),
+        None => (value, "s"),
+    };
+    let amount = digits
+        .parse::<u64>()
+        .map_err(|_| format!("invalid duration `{}`", value))?;
+    let scale = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => {
+            return Err(format!(
+                "invalid duration unit `{}`, expected s, m, h or d",
+                unit
+            ))
+        }
+    };
+    amount
+        .checked_mul(scale)
+        .ok_or_else(|| format!("duration `{}` is too large", value))
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_max_age;
+
+    #[test]
+    fn parses_max_age() {
+        #[rustfmt::skip]
+        let cases = [("90", Some(90)), ("90s", Some(90)), ("5m", Some(300)), ("2h", Some(7_200)), ("1d", Some(86_400)), ("m", None), ("5w", None), ("999999999999999999d", None)];
+        for (value, expected) in cases {
+            assert_eq!(parse_max_age(value).ok(), expected, "{}", value);
+        }
     }
 }
@@ -1,15 +1,32 @@
 //! `submit` subcommand.
 
+use super::{
+    comment, draft,
+    overrides::{read_body_file, PullRequestFields},
+    template::PullRequestTemplate,
+};
 use crate::{
+    codeowners::CodeOwners,
+    config::{MilestoneRef, StackOverviewMode},
     ctx::StContext,
     errors::{StError, StResult},
     git::RepositoryExt,
+    github::{concurrently, is_not_found, GitHubClient},
     tree::RemoteMetadata,
 };
 use clap::Args;
 use git2::BranchType;
 use nu_ansi_term::Color;
-use octocrab::{issues::IssueHandler, models::CommentId, pulls::PullRequestHandler, Octocrab};
+use octocrab::{
+    issues::IssueHandler,
+    models::{pulls::PullRequest, CommentId, Milestone},
+    params::State,
+    pulls::PullRequestHandler,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+};
 
 /// CLI arguments for the `submit` subcommand.
 #[derive(Debug, Clone, Eq, PartialEq, Args)]
@@ -17,60 +34,182 @@ pub struct SubmitCmd {
     /// Force the submission of the stack, analogous to `git push --force`.
     #[clap(long, short)]
     force: bool,
+    /// The pull request template to prefill new pull request descriptions with, by file name
+    /// without the extension. Prompts for a template if the repository has several.
+    #[clap(long, short)]
+    template: Option<String>,
+    /// Open new pull requests without prompting, using titles and descriptions computed from the
+    /// commits of each branch.
+    #[clap(long)]
+    fill: bool,
+    /// The title of new pull requests.
+    #[clap(long)]
+    title: Option<String>,
+    /// The description of new pull requests.
+    #[clap(long, conflicts_with = "body_file")]
+    body: Option<String>,
+    /// Read the description of new pull requests from a file, or from stdin if `-`.
+    #[clap(long)]
+    body_file: Option<PathBuf>,
+    /// Open new pull requests as drafts.
+    #[clap(long, conflicts_with = "ready")]
+    draft: bool,
+    /// Open new pull requests as ready for review.
+    #[clap(long)]
+    ready: bool,
+    /// Request reviews on new pull requests from a user, or an `org/team` team. May be repeated
+    /// or comma-separated.
+    #[clap(long = "reviewer", value_delimiter = ',')]
+    reviewers: Vec<String>,
+    /// Add a label to new pull requests. May be repeated or comma-separated.
+    #[clap(long = "label", value_delimiter = ',')]
+    labels: Vec<String>,
+    /// Assign a user to new pull requests. May be repeated or comma-separated.
+    #[clap(long = "assignee", value_delimiter = ',')]
+    assignees: Vec<String>,
+    /// A TOML or JSON file mapping branch names to the metadata of their new pull requests, with
+    /// the keys `title`, `body`, `draft`, `reviewers`, `labels` and `assignees`. Takes precedence
+    /// over the flags above.
+    #[clap(long)]
+    metadata_file: Option<PathBuf>,
+    /// Submit only the current branch.
+    #[clap(long, group = "scope")]
+    only: bool,
+    /// Submit the current branch and the branches below it, down to trunk.
+    #[clap(long, group = "scope")]
+    downstack: bool,
+    /// Submit the current branch and every branch stacked on top of it, including all forks.
+    #[clap(long, group = "scope")]
+    upstack: bool,
+    /// Submit every tracked branch.
+    #[clap(long, group = "scope")]
+    all: bool,
+    /// Choose the branches to submit.
+    #[clap(long, group = "scope")]
+    select: bool,
 }
 
 impl SubmitCmd {
     /// Run the `submit` subcommand.
     pub async fn run(self, mut ctx: StContext<'_>) -> StResult<()> {
         // Establish the GitHub API client.
-        let gh_client = Octocrab::builder()
-            .personal_token(ctx.cfg.github_token.clone())
-            .build()?;
-        let (owner, repo) = ctx.owner_and_repository()?;
-        let mut pulls = gh_client.pulls(&owner, &repo);
+        let gh_client = ctx.github_client()?;
+        let fork = ctx.fork_owner_and_repository()?;
 
-        // Resolve the active stack.
-        let stack = ctx.discover_stack()?;
+        // Resolve the branches to submit, and the milestone to add new PRs to.
+        let mut branches = self.resolve_scope(&ctx)?;
+        let milestone = match ctx.cfg.submit.milestone.clone() {
+            Some(MilestoneRef::Number(number)) => Some(number),
+            Some(MilestoneRef::Title(title)) => {
+                Some(Self::resolve_milestone(&gh_client, &title).await?)
+            }
+            None => None,
+        };
 
         // Perform pre-flight checks.
         println!("🔍 Checking for closed pull requests...");
-        self.pre_flight(&mut ctx, &stack, &mut pulls).await?;
+        self.pre_flight(&mut ctx, &branches, &gh_client).await?;
+        branches.retain(|branch| ctx.tree.get(branch).is_some());
 
         // Submit the stack.
         println!(
             "\n🐙 Submitting changes to remote `{}`...",
-            Color::Blue.paint("origin")
+            Color::Blue.paint(ctx.push_remote_name())
         );
-        self.submit_stack(&mut ctx, &mut pulls, &owner, &repo)
+        self.submit_stack(&mut ctx, &gh_client, &branches, fork.as_ref(), milestone)
             .await?;
 
+        // Keep the pull requests above the lowest open one of each stack in draft.
+        if ctx.cfg.submit.draft_upstack {
+            println!("\n📌 Keeping upstack pull requests in draft...");
+            draft::keep_upstack_draft(&ctx, &gh_client, &branches).await?;
+        }
+
         // Update the stack navigation comments on the PRs.
         println!("\n📝 Updating stack navigation comments...");
-        self.update_pr_comments(&mut ctx, gh_client.issues(owner, repo), &stack)
+        self.update_pr_comments(&mut ctx, &gh_client, &branches, fork.as_ref())
             .await?;
 
         println!("\n🧙💫 All pull requests up to date.");
         Ok(())
     }
 
+    /// Resolves the branches to submit from the selected scope, defaulting to the current stack.
+    ///
+    /// ## Returns
+    /// - `Result<Vec<String>>` - The branches to submit, excluding trunks. Parents are guaranteed
+    ///   to be listed before their children.
+    fn resolve_scope(&self, ctx: &StContext<'_>) -> StResult<Vec<String>> {
+        let current = ctx.repository.current_branch_name()?;
+        let selected = if self.only {
+            vec![current]
+        } else if self.downstack {
+            let mut downstack = ctx.tree.ancestors(&current)?;
+            downstack.push(current);
+            downstack
+        } else if self.upstack {
+            ctx.tree.subtree(&current)?
+        } else if self.all {
+            ctx.tree.branches()?
+        } else if self.select {
+            let candidates = ctx
+                .tree
+                .branches()?
+                .into_iter()
+                .filter(|b| !ctx.tree.is_trunk(b))
+                .collect::<Vec<_>>();
+            let defaults = candidates
+                .iter()
+                .enumerate()
+                .filter(|(_, b)| **b == current)
+                .map(|(i, _)| i)
+                .collect::<Vec<_>>();
+            inquire::MultiSelect::new("Select branches to submit:", candidates)
+                .with_default(&defaults)
+                .prompt()?
+        } else {
+            ctx.discover_stack()?
+        };
+
+        // Order the selection by the tree, so that parents are pushed before their children.
+        Ok(ctx
+            .tree
+            .branches()?
+            .into_iter()
+            .filter(|b| !ctx.tree.is_trunk(b) && selected.contains(b))
+            .collect())
+    }
+
+    /// Returns the base of the PR for `branch_name`: its nearest ancestor that is a trunk, has a
+    /// PR, or is submitted along with it.
+    fn pr_base(ctx: &StContext<'_>, branch_name: &str, branches: &[String]) -> StResult<String> {
+        let ancestors = ctx.tree.ancestors(branch_name)?;
+        let base = ancestors.iter().rev().find(|ancestor| {
+            ctx.tree
+                .get(ancestor)
+                .is_some_and(|b| b.parent.is_none() || b.remote.is_some())
+                || branches.contains(ancestor)
+        });
+        match base {
+            Some(base) => Ok(base.clone()),
+            None => Ok(ctx.tree.trunk_of(branch_name)?.to_string()),
+        }
+    }
+
     /// Performs pre-flight checks before submitting the stack.
     async fn pre_flight(
         &self,
         ctx: &mut StContext<'_>,
-        stack: &[String],
-        pulls: &mut PullRequestHandler<'_>,
+        branches: &[String],
+        gh_client: &GitHubClient,
     ) -> StResult<()> {
         // Return early if the stack is not restacked or the current working tree is dirty.
-        ctx.check_cleanliness(stack)?;
+        ctx.check_cleanliness(branches)?;
 
         // Check if any PRs have been closed, and offer to delete them before starting the submission process.
-        let num_closed = ctx
-            .delete_closed_branches(
-                stack.iter().skip(1).cloned().collect::<Vec<_>>().as_slice(),
-                pulls,
-            )
-            .await?;
+        let num_closed = ctx.delete_closed_branches(branches, gh_client).await?;
 
+        #[allow(clippy::obfuscated_if_else)]
         if num_closed > 0 {
             println!(
                 "Deleted {} closed pull request{}. Run `{}` to re-stack the branches.",
@@ -83,19 +222,86 @@ impl SubmitCmd {
         Ok(())
     }
 
-    /// Submits the stack of branches to GitHub.
+    /// Submits the branches to GitHub, in order. The base of each PR is the nearest submitted
+    /// ancestor of its branch.
+    ///
+    /// If `fork` is set, branches are pushed to the fork, and only branches based on trunk are
+    /// opened as pull requests, because the base of a pull request must exist in the upstream
+    /// repository.
     async fn submit_stack(
         &self,
         ctx: &mut StContext<'_>,
-        pulls: &mut PullRequestHandler<'_>,
-        owner: &str,
-        repo: &str,
+        gh_client: &GitHubClient,
+        branches: &[String],
+        fork: Option<&(String, String)>,
+        milestone: Option<u64>,
     ) -> StResult<()> {
-        let stack = ctx.discover_stack()?;
+        let (pulls, issues) = (gh_client.pulls(), gh_client.issues());
+        let (owner, repo) = (gh_client.owner.as_str(), gh_client.repo.as_str());
+        let (cli_fields, overrides) = self.pull_request_fields()?;
+        let remote = ctx.push_remote_name().to_string();
+        let host = ctx.github_host()?;
+        let root = ctx
+            .repository
+            .workdir()
+            .unwrap_or_else(|| ctx.repository.path());
+        let templates = PullRequestTemplate::discover(root);
+        let mut selected_template = None;
+        let mut codeowners = HashMap::<String, Option<CodeOwners>>::new();
+
+        // Recover the PRs of branches whose remote metadata was lost, rather than opening second
+        // ones.
+        let unsubmitted = branches
+            .iter()
+            .filter(|b| ctx.tree.get(b).is_some_and(|b| b.remote.is_none()))
+            .cloned()
+            .collect::<Vec<_>>();
+        let head_owner = fork.map_or(owner, |(fork_owner, _)| fork_owner.as_str());
+        let recovered = concurrently(unsubmitted.iter().cloned(), |branch| {
+            let (gh_client, head_owner) = (gh_client.clone(), head_owner.to_string());
+            async move { Self::find_open_pr(&gh_client.pulls(), &head_owner, &branch).await }
+        })
+        .await?;
+        for (branch, number) in unsubmitted.iter().zip(recovered) {
+            let Some(number) = number else {
+                continue;
+            };
+            ctx.tree
+                .get_mut(branch)
+                .ok_or_else(|| StError::BranchNotTracked(branch.to_string()))?
+                .remote = Some(RemoteMetadata::new(number));
+            println!(
+                "Found existing pull request #{} for branch `{}`.",
+                number,
+                Color::Green.paint(branch)
+            );
+        }
 
-        // Iterate over the stack and submit PRs.
-        for (i, branch) in stack.iter().enumerate().skip(1) {
-            let parent = &stack[i - 1];
+        // Fetch the existing PRs concurrently.
+        let numbers = branches
+            .iter()
+            .filter_map(|b| ctx.tree.get(b).and_then(|b| b.remote.as_ref()))
+            .map(|r| r.pr_number)
+            .collect::<Vec<_>>();
+        let mut remote_prs = numbers
+            .iter()
+            .copied()
+            .zip(gh_client.pull_requests(&numbers).await?)
+            .collect::<HashMap<_, _>>();
+
+        // Iterate over the branches and submit PRs. Pushes stay sequential, so that each branch
+        // is pushed after its parent.
+        for branch in branches {
+            let base = Self::pr_base(ctx, branch, branches)?;
+            let base_is_trunk = ctx.tree.is_trunk(&base);
+            let branch_codeowners = match ctx.cfg.submit.codeowners {
+                true => codeowners
+                    .entry(base.clone())
+                    .or_insert_with(|| Self::codeowners_of(ctx, &base))
+                    .as_ref(),
+                false => None,
+            };
+            let required = Self::required_participants(ctx, branch_codeowners, branch, &base)?;
 
             let tracked_branch = ctx
                 .tree
@@ -106,23 +312,28 @@ impl SubmitCmd {
                 // If the PR has already been submitted.
 
                 // Grab remote metadata for the pull request.
-                let remote_pr = pulls.get(remote_meta.pr_number).await?;
+                let remote_pr = remote_prs
+                    .remove(&remote_meta.pr_number)
+                    .ok_or(StError::PullRequestNotFound)?;
 
                 // Check if the PR base needs to be updated
-                if &remote_pr.base.ref_field != parent {
+                if remote_pr.base.ref_field != base {
                     // Update the PR base.
                     pulls
                         .update(remote_meta.pr_number)
-                        .base(parent)
+                        .base(&base)
                         .send()
                         .await?;
                     println!(
                         "-> Updated base branch for pull request for branch `{}` to `{}`.",
                         Color::Green.paint(branch),
-                        Color::Yellow.paint(parent)
+                        Color::Yellow.paint(&base)
                     );
                 }
 
+                // Add newly required reviewers, labels and assignees.
+                Self::add_participants(&pulls, &issues, &remote_pr, &required, milestone).await?;
+
                 // Check if the local branch is ahead of the remote.
                 let remote_synced = remote_pr.head.sha
                     == ctx
@@ -141,7 +352,7 @@ impl SubmitCmd {
                 }
 
                 // Push the branch to the remote.
-                ctx.repository.push_branch(branch, "origin", self.force)?;
+                ctx.repository.push_branch(branch, &remote, self.force)?;
 
                 // Print success message.
                 println!("Updated branch `{}` on remote.", Color::Green.paint(branch));
@@ -149,14 +360,76 @@ impl SubmitCmd {
                 // If the PR has not been submitted yet.
 
                 // Push the branch to the remote.
-                ctx.repository.push_branch(branch, "origin", self.force)?;
+                ctx.repository.push_branch(branch, &remote, self.force)?;
+
+                // When contributing from a fork, dependent branches are opened once their base
+                // has been merged.
+                if fork.is_some() && !base_is_trunk {
+                    println!(
+                        "Pushed branch `{}` to `{}`. Its pull request will be opened once `{}` is merged.",
+                        Color::Green.paint(branch),
+                        Color::Blue.paint(&remote),
+                        Color::Yellow.paint(&base)
+                    );
+                    continue;
+                }
+
+                // Compute the defaults from the commits of the branch.
+                let fields = cli_fields.overridden_by(overrides.get(branch.as_str()));
+                let template = match fields.body {
+                    Some(_) => None,
+                    None => {
+                        // Select the template once, and reuse the choice for every new PR.
+                        if selected_template.is_none() {
+                            selected_template = Some(PullRequestTemplate::select(
+                                &templates,
+                                self.template.as_deref(),
+                                !self.fill,
+                            )?);
+                        }
+                        selected_template.clone().flatten()
+                    }
+                };
+                let messages = ctx
+                    .repository
+                    .branch_commits(branch, &base)?
+                    .iter()
+                    .map(|c| {
+                        (
+                            c.summary().unwrap_or_default().to_string(),
+                            c.body().unwrap_or_default().to_string(),
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                let defaults = Self::default_pr_metadata(
+                    branch,
+                    &messages,
+                    template.as_ref().map(|t| t.body.as_str()),
+                );
+
+                let metadata = PRCreationMetadata {
+                    title: fields.title.clone().unwrap_or(defaults.title),
+                    body: fields.body.clone().unwrap_or(defaults.body),
+                    is_draft: fields.draft.unwrap_or(defaults.is_draft),
+                };
 
-                // Prompt the user for PR metadata.
-                let metadata = Self::prompt_pr_metadata(branch, parent)?;
+                // Prompt the user for the remaining PR metadata, unless the defaults were
+                // accepted up front.
+                let metadata = if self.fill {
+                    metadata
+                } else {
+                    Self::prompt_pr_metadata(branch, &base, metadata, &fields)?
+                };
+
+                // Pull requests from a fork reference the head branch by its owner.
+                let head = match fork {
+                    Some((fork_owner, _)) => format!("{}:{}", fork_owner, branch),
+                    None => branch.clone(),
+                };
 
                 // Submit PR.
                 let pr_info = pulls
-                    .create(metadata.title, branch, parent)
+                    .create(metadata.title, head, &base)
                     .body(metadata.body)
                     .draft(metadata.is_draft)
                     .send()
@@ -165,11 +438,12 @@ impl SubmitCmd {
                 // Update the tracked branch with the remote information.
                 tracked_branch.remote = Some(RemoteMetadata::new(pr_info.number));
 
+                // Request reviews, and add labels, assignees and the milestone.
+                let participants = fields.with_participants_of(&required);
+                Self::add_participants(&pulls, &issues, &pr_info, &participants, milestone).await?;
+
                 // Print success message.
-                let pr_link = format!(
-                    "https://github.com/{}/{}/pull/{}",
-                    owner, repo, pr_info.number
-                );
+                let pr_link = host.pull_request_url(owner, repo, pr_info.number);
                 println!(
                     "Submitted new pull request for branch `{}` @ `{}`",
                     Color::Green.paint(branch),
@@ -181,115 +455,495 @@ impl SubmitCmd {
         Ok(())
     }
 
-    /// Updates the comments on a PR with the current stack information.
+    /// Collects the metadata of new PRs passed on the command line, and the per-branch overrides
+    /// of the metadata file, if any.
+    fn pull_request_fields(
+        &self,
+    ) -> StResult<(PullRequestFields, BTreeMap<String, PullRequestFields>)> {
+        let non_empty = |values: &[String]| (!values.is_empty()).then(|| values.to_vec());
+        let body = match &self.body_file {
+            Some(path) => Some(read_body_file(path)?),
+            None => self.body.clone(),
+        };
+        let draft = match (self.draft, self.ready) {
+            (true, _) => Some(true),
+            (_, true) => Some(false),
+            _ => None,
+        };
+
+        let fields = PullRequestFields {
+            title: self.title.clone(),
+            body,
+            draft,
+            reviewers: non_empty(&self.reviewers),
+            labels: non_empty(&self.labels),
+            assignees: non_empty(&self.assignees),
+        };
+        let overrides = match &self.metadata_file {
+            Some(path) => PullRequestFields::load_overrides(path)?,
+            None => BTreeMap::new(),
+        };
+        Ok((fields, overrides))
+    }
+
+    /// Returns the participants that every PR for `branch_name` requires: the reviewers, labels
+    /// and assignees of the configuration, and the code owners of the files changed since `base`.
+    fn required_participants(
+        ctx: &StContext<'_>,
+        codeowners: Option<&CodeOwners>,
+        branch_name: &str,
+        base: &str,
+    ) -> StResult<PullRequestFields> {
+        let config = &ctx.cfg.submit;
+        let non_empty = |values: Vec<String>| (!values.is_empty()).then_some(values);
+
+        let mut reviewers = config.reviewers.clone();
+        if let Some(codeowners) = codeowners {
+            let changed = ctx.repository.changed_files(branch_name, base)?;
+            reviewers.extend(codeowners.owners_of_all(changed.iter().map(String::as_str)));
+        }
+
+        let required = PullRequestFields {
+            labels: non_empty(config.labels.clone()),
+            assignees: non_empty(config.assignees.clone()),
+            ..Default::default()
+        };
+        Ok(required.with_participants_of(&PullRequestFields {
+            reviewers: non_empty(reviewers),
+            ..Default::default()
+        }))
+    }
+
+    /// Loads the code owners that GitHub applies to PRs against `base`: those of the `CODEOWNERS`
+    /// file on the base branch as last fetched from the remote, or on the local branch if the
+    /// remote has not been fetched.
+    fn codeowners_of(ctx: &StContext<'_>, base: &str) -> Option<CodeOwners> {
+        let remote_ref = format!("refs/remotes/{}/{}", ctx.remote_name(), base);
+        let revision = match ctx.repository.find_reference(&remote_ref) {
+            Ok(_) => remote_ref,
+            Err(_) => format!("refs/heads/{}", base),
+        };
+        CodeOwners::load(ctx.repository, &revision)
+    }
+
+    /// Looks up the number of the open milestone with the given title.
+    async fn resolve_milestone(gh_client: &GitHubClient, title: &str) -> StResult<u64> {
+        let milestones: Vec<Milestone> = gh_client
+            .octocrab()
+            .get(
+                format!("/repos/{}/{}/milestones", gh_client.owner, gh_client.repo),
+                Some(&[("state", "open"), ("per_page", "100")]),
+            )
+            .await?;
+        milestones
+            .into_iter()
+            .find(|m| m.title == title)
+            .map(|m| m.number as u64)
+            .ok_or_else(|| StError::MilestoneNotFound(title.to_string()))
+    }
+
+    /// Requests reviews on a PR, and adds labels, assignees and a milestone to it. Reviewers,
+    /// labels and assignees that were added before are kept, and the milestone is only set if the
+    /// PR has none.
+    ///
+    /// Reviewers of the form `org/team` are requested as teams. The author of the PR, reviewers
+    /// that are already requested, and users that already reviewed the PR are skipped.
+    async fn add_participants(
+        pulls: &PullRequestHandler<'_>,
+        issues: &IssueHandler<'_>,
+        pr: &PullRequest,
+        fields: &PullRequestFields,
+        milestone: Option<u64>,
+    ) -> StResult<()> {
+        let contains =
+            |values: &[&str], value: &str| values.iter().any(|v| v.eq_ignore_ascii_case(value));
+
+        // Request reviews from the reviewers that are not involved yet. Users and teams are kept
+        // apart, as a team may share its slug with a user.
+        let mut involved_users = pr
+            .requested_reviewers
+            .iter()
+            .flatten()
+            .map(|a| a.login.clone())
+            .chain(pr.user.as_ref().map(|u| u.login.clone()))
+            .collect::<Vec<_>>();
+        let involved_teams = pr
+            .requested_teams
+            .iter()
+            .flatten()
+            .map(|t| t.slug.as_str())
+            .collect::<Vec<_>>();
+        let mut reviewers = fields.reviewers.clone().unwrap_or_default();
+        reviewers.retain(|r| match r.split_once('/') {
+            Some((_, slug)) => !contains(&involved_teams, slug),
+            None => !involved_users.iter().any(|u| u.eq_ignore_ascii_case(r)),
+        });
+        if reviewers.iter().any(|r| !r.contains('/')) {
+            let reviews = pulls.list_reviews(pr.number).per_page(100).send().await?;
+            involved_users.extend(
+                reviews
+                    .items
+                    .into_iter()
+                    .filter_map(|r| r.user.map(|u| u.login)),
+            );
+            let involved_users = involved_users
+                .iter()
+                .map(String::as_str)
+                .collect::<Vec<_>>();
+            reviewers.retain(|r| r.contains('/') || !contains(&involved_users, r));
+        }
+        if !reviewers.is_empty() {
+            let (teams, users): (Vec<_>, Vec<_>) = reviewers.iter().partition(|r| r.contains('/'));
+            let users = users.into_iter().cloned().collect::<Vec<_>>();
+            let teams = teams
+                .into_iter()
+                .filter_map(|t| t.split_once('/').map(|(_, slug)| slug.to_string()))
+                .collect::<Vec<_>>();
+            pulls.request_reviews(pr.number, users, teams).await?;
+            println!(
+                "-> Requested reviews from {}.",
+                reviewers
+                    .iter()
+                    .map(|r| format!("`{}`", Color::Blue.paint(r)))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+
+        // Add the labels and assignees that are missing.
+        let labels = pr
+            .labels
+            .iter()
+            .flatten()
+            .map(|l| l.name.as_str())
+            .collect::<Vec<_>>();
+        let labels = fields
+            .labels
+            .iter()
+            .flatten()
+            .filter(|l| !contains(&labels, l))
+            .cloned()
+            .collect::<Vec<_>>();
+        if !labels.is_empty() {
+            issues.add_labels(pr.number, &labels).await?;
+        }
+
+        let assignees = pr
+            .assignees
+            .iter()
+            .flatten()
+            .map(|a| a.login.as_str())
+            .collect::<Vec<_>>();
+        let assignees = fields
+            .assignees
+            .iter()
+            .flatten()
+            .map(String::as_str)
+            .filter(|a| !contains(&assignees, a))
+            .collect::<Vec<_>>();
+        if !assignees.is_empty() {
+            issues.add_assignees(pr.number, &assignees).await?;
+        }
+
+        if let (Some(milestone), None) = (milestone, pr.milestone.as_ref()) {
+            issues.update(pr.number).milestone(milestone).send().await?;
+        }
+        Ok(())
+    }
+
+    /// Updates the stack comments on the PRs of every stack that contains a submitted branch.
+    ///
+    /// Each comment renders the whole subtree above the trunk that contains its PR, including forks.
+    /// Depending on [StackOverviewMode], the overview is kept in a comment or in the PR description.
     async fn update_pr_comments(
         &self,
         ctx: &mut StContext<'_>,
-        issue_handler: IssueHandler<'_>,
-        stack: &[String],
+        gh_client: &GitHubClient,
+        branches: &[String],
+        fork: Option<&(String, String)>,
     ) -> StResult<()> {
-        for branch in stack.iter().skip(1) {
-            let tracked_branch = ctx
-                .tree
-                .get_mut(branch)
-                .ok_or_else(|| StError::BranchNotTracked(branch.to_string()))?;
+        // Collect the branches of the subtrees touched by the submission.
+        let mut targets = Vec::<String>::new();
+        for branch in branches {
+            let root = comment::stack_root(&ctx.tree, branch)?;
+            for stacked in ctx.tree.subtree(&root)? {
+                if !targets.contains(&stacked) {
+                    targets.push(stacked);
+                }
+            }
+        }
 
+        // Fetch the status of the PRs once, to render every overview from.
+        let (pulls, issue_handler) = (gh_client.pulls(), gh_client.issues());
+        let statuses = ctx
+            .refresh_pull_requests(gh_client, &targets)
+            .await?
+            .into_values()
+            .map(|status| (status.number, status))
+            .collect::<HashMap<_, _>>();
+
+        let root = ctx
+            .repository
+            .workdir()
+            .unwrap_or_else(|| ctx.repository.path());
+        let template = comment::load_template(root, ctx.cfg.submit.comment_template.as_deref())?;
+        let host = ctx.github_host()?;
+        let mode = ctx.cfg.submit.stack_overview;
+        let mut viewer = None;
+
+        for branch in targets.iter() {
             // Skip branches that are not submitted as PRs.
-            let Some(remote_meta) = tracked_branch.remote else {
+            let Some(remote_meta) = ctx.tree.get(branch).and_then(|b| b.remote.clone()) else {
                 continue;
             };
 
-            // If the PR has been submitted, update the comment.
-            // If the PR is new, create a new comment.
-            let rendered_comment = Self::render_pr_comment(ctx, branch, stack)?;
-            match remote_meta.comment_id {
-                Some(id) => {
-                    // Update the existing comment.
-                    issue_handler
-                        .update_comment(CommentId(id), rendered_comment)
+            let rendered_comment =
+                comment::render(&template, &ctx.tree, branch, &statuses, |parent, head| {
+                    fork.map(|(fork_owner, fork_repo)| {
+                        host.compare_url(fork_owner, fork_repo, parent, head)
+                    })
+                })?;
+            let body = &statuses
+                .get(&remote_meta.pr_number)
+                .ok_or(StError::PullRequestNotFound)?
+                .body;
+
+            if mode == StackOverviewMode::Body {
+                // Replace the overview section of the description, leaving the rest untouched.
+                let new_body = comment::embed_in_body(body, &rendered_comment);
+                if &new_body != body {
+                    pulls
+                        .update(remote_meta.pr_number)
+                        .body(new_body)
+                        .send()
                         .await?;
                 }
-                None => {
-                    // Create a new comment.
-                    let comment_info = issue_handler
-                        .create_comment(remote_meta.pr_number, rendered_comment)
-                        .await?;
 
-                    // Get a new mutable reference to the branch and update the comment ID.
+                // Clean up the comment left over from the comment mode.
+                if let Some(id) = remote_meta.comment_id {
+                    match issue_handler.delete_comment(CommentId(id)).await {
+                        Err(e) if !is_not_found(&e) => return Err(e.into()),
+                        _ => {}
+                    }
                     ctx.tree
                         .get_mut(branch)
                         .expect("Must exist")
                         .remote
                         .as_mut()
                         .expect("Must exist")
-                        .comment_id = Some(comment_info.id.0);
+                        .comment_id = None;
                 }
+                continue;
             }
+
+            // Clean up the section left over from the body mode.
+            if let Some(stripped) = comment::strip_from_body(body) {
+                pulls
+                    .update(remote_meta.pr_number)
+                    .body(stripped)
+                    .send()
+                    .await?;
+            }
+
+            // Recover the comment by its marker if its ID was lost.
+            let rendered_comment = format!("{}\n{}", comment::COMMENT_MARKER, rendered_comment);
+            let comment_id = match remote_meta.comment_id {
+                Some(id) => Some(id),
+                None => {
+                    if viewer.is_none() {
+                        viewer = Some(gh_client.viewer_login().await?);
+                    }
+                    let viewer = viewer.as_deref().unwrap_or_default();
+                    Self::find_stack_comment(&issue_handler, remote_meta.pr_number, viewer).await?
+                }
+            };
+
+            // If the comment exists, update it. If it is new, or was deleted on GitHub, create a
+            // new comment.
+            let updated = match comment_id {
+                Some(id) => match issue_handler
+                    .update_comment(CommentId(id), rendered_comment.clone())
+                    .await
+                {
+                    Ok(comment_info) => Some(comment_info.id.0),
+                    Err(e) if is_not_found(&e) => None,
+                    Err(e) => return Err(e.into()),
+                },
+                None => None,
+            };
+            let comment_id = match updated {
+                Some(id) => id,
+                None => {
+                    issue_handler
+                        .create_comment(remote_meta.pr_number, rendered_comment)
+                        .await?
+                        .id
+                        .0
+                }
+            };
+
+            // Get a new mutable reference to the branch and update the comment ID.
+            ctx.tree
+                .get_mut(branch)
+                .expect("Must exist")
+                .remote
+                .as_mut()
+                .expect("Must exist")
+                .comment_id = Some(comment_id);
         }
         Ok(())
     }
 
-    /// Prompts the user for metadata about the PR during the initial submission process.
-    fn prompt_pr_metadata(branch_name: &str, parent_name: &str) -> StResult<PRCreationMetadata> {
-        let title = inquire::Text::new(
-            format!(
-                "Title of pull request (`{}` -> `{}`):",
-                Color::Green.paint(branch_name),
-                Color::Yellow.paint(parent_name)
-            )
-            .as_str(),
-        )
-        .prompt()?;
-        let body = inquire::Editor::new("Pull request description")
-            .with_file_extension(".md")
-            .prompt()?;
-        let is_draft = inquire::Confirm::new("Is this PR a draft? (default: yes)")
-            .with_default(true)
-            .prompt()?;
+    /// Finds the open PR of a branch, e.g. one opened before the stack metadata was lost.
+    ///
+    /// ## Takes
+    /// - `pulls` - The pull request handler of the repository.
+    /// - `head_owner` - The owner of the repository that the branch is pushed to.
+    /// - `branch` - The name of the branch.
+    ///
+    /// ## Returns
+    /// - `Result<Option<u64>>` - The number of the open PR with the branch as its head, if any.
+    async fn find_open_pr(
+        pulls: &PullRequestHandler<'_>,
+        head_owner: &str,
+        branch: &str,
+    ) -> StResult<Option<u64>> {
+        let open = pulls
+            .list()
+            .state(State::Open)
+            .head(format!("{}:{}", head_owner, branch))
+            .per_page(1)
+            .send()
+            .await?;
+        Ok(open.items.first().map(|pr| pr.number))
+    }
 
-        Ok(PRCreationMetadata {
+    /// Finds the stack comment of a PR by its hidden marker. Only comments of the authenticated
+    /// user are considered, as comments of other users cannot be updated, and may quote the marker.
+    ///
+    /// ## Takes
+    /// - `issues` - The issue handler of the repository.
+    /// - `pr_number` - The number of the PR.
+    /// - `viewer` - The login of the authenticated user.
+    ///
+    /// ## Returns
+    /// - `Result<Option<u64>>` - The ID of the oldest stack comment of the user on the PR, if any.
+    async fn find_stack_comment(
+        issues: &IssueHandler<'_>,
+        pr_number: u64,
+        viewer: &str,
+    ) -> StResult<Option<u64>> {
+        for page in 1u32.. {
+            let comments = issues
+                .list_comments(pr_number)
+                .per_page(100)
+                .page(page)
+                .send()
+                .await?;
+            let found = comments.items.iter().find(|c| {
+                c.user.login.eq_ignore_ascii_case(viewer)
+                    && c.body
+                        .as_deref()
+                        .is_some_and(|body| body.contains(comment::COMMENT_MARKER))
+            });
+            if let Some(found) = found {
+                return Ok(Some(found.id.0));
+            }
+            if comments.next.is_none() {
+                break;
+            }
+        }
+        Ok(None)
+    }
+
+    /// Computes the default metadata of a new PR from the commits of its branch.
+    ///
+    /// The title is the subject of the branch's only commit, or the branch name if it has several.
+    /// The body holds the bodies of the commit messages, followed by the PR template, if any.
+    ///
+    /// ## Takes
+    /// - `branch_name` - The name of the branch.
+    /// - `messages` - The subject and body of each commit of the branch, oldest first.
+    /// - `template` - The body of the PR template to use, if any.
+    ///
+    /// ## Returns
+    /// - `PRCreationMetadata` - The default metadata. New PRs are drafts by default.
+    fn default_pr_metadata(
+        branch_name: &str,
+        messages: &[(String, String)],
+        template: Option<&str>,
+    ) -> PRCreationMetadata {
+        let title = match messages {
+            [(subject, _)] if !subject.trim().is_empty() => subject.trim().to_string(),
+            _ => branch_name.to_string(),
+        };
+
+        let body = messages
+            .iter()
+            .map(|(_, body)| body.trim())
+            .chain(template.map(str::trim))
+            .filter(|section| !section.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n\n");
+
+        PRCreationMetadata {
             title,
             body,
-            is_draft,
-        })
+            is_draft: true,
+        }
     }
 
-    /// Renders the PR comment body for the current stack.
-    fn render_pr_comment(
-        ctx: &StContext<'_>,
-        current_branch: &str,
-        stack: &[String],
-    ) -> StResult<String> {
-        let mut comment = String::new();
-        comment.push_str("## 📚 $\\text{Stack Overview}$\n\n");
-        comment.push_str("Pulls submitted in this stack:\n");
-
-        // Display all branches in the stack.
-        for branch in stack.iter().skip(1).rev() {
-            let tracked_branch = ctx
-                .tree
-                .get(branch)
-                .ok_or_else(|| StError::BranchNotTracked(branch.to_string()))?;
-            if let Some(remote) = tracked_branch.remote {
-                comment.push_str(&format!(
-                    "* #{}{}\n",
-                    remote.pr_number,
-                    (branch == current_branch)
-                        .then_some(" 👈")
-                        .unwrap_or_default()
-                ));
-            }
+    /// Prompts the user for metadata about the PR during the initial submission process, prefilled
+    /// with `defaults`. Fields that are set in `fields` are not prompted for.
+    fn prompt_pr_metadata(
+        branch_name: &str,
+        parent_name: &str,
+        defaults: PRCreationMetadata,
+        fields: &PullRequestFields,
+    ) -> StResult<PRCreationMetadata> {
+        if fields.title.is_some() && fields.body.is_some() && fields.draft.is_some() {
+            return Ok(defaults);
         }
-        comment.push_str(format!("* `{}`\n", ctx.tree.trunk_name).as_str());
 
-        comment.push_str(
-            "\n_This comment was automatically generated by [`st`](https://github.com/clabby/st)._",
-        );
-        Ok(comment)
+        let title = if fields.title.is_some() {
+            defaults.title
+        } else {
+            inquire::Text::new(
+                format!(
+                    "Title of pull request (`{}` -> `{}`):",
+                    Color::Green.paint(branch_name),
+                    Color::Yellow.paint(parent_name)
+                )
+                .as_str(),
+            )
+            .with_initial_value(&defaults.title)
+            .prompt()?
+        };
+        let body = if fields.body.is_some() {
+            defaults.body
+        } else {
+            inquire::Editor::new("Pull request description")
+                .with_file_extension(".md")
+                .with_predefined_text(&defaults.body)
+                .prompt()?
+        };
+        let is_draft = match fields.draft {
+            Some(is_draft) => is_draft,
+            None => inquire::Confirm::new("Is this PR a draft? (default: yes)")
+                .with_default(defaults.is_draft)
+                .prompt()?,
+        };
+
+        Ok(PRCreationMetadata {
+            title,
+            body,
+            is_draft,
+        })
     }
 }
 
 /// Metadata about pull request creation.
+#[derive(Debug, Clone, Eq, PartialEq)]
 struct PRCreationMetadata {
     /// Title of the pull request.
     title: String,
@@ -298,3 +952,333 @@ struct PRCreationMetadata {
     /// Whether or not the pull request is a draft.
     is_draft: bool,
 }
+
+#[cfg(test)]
+mod test {
+    use super::SubmitCmd;
+    use crate::{
+        config::StConfig,
+        ctx::StContext,
+        github::{
+            fixtures::{comment, git, pull, pull_status, user},
+            mock::MockGitHub,
+        },
+        subcommands::remote::{comment::COMMENT_MARKER, overrides::PullRequestFields},
+        tree::RemoteMetadata,
+    };
+    use git2::Repository;
+    use serde_json::json;
+
+    /// A `submit` that opens new PRs without prompting.
+    fn fill() -> SubmitCmd {
+        SubmitCmd {
+            force: false,
+            template: None,
+            fill: true,
+            title: None,
+            body: None,
+            body_file: None,
+            draft: false,
+            ready: false,
+            reviewers: Vec::new(),
+            labels: Vec::new(),
+            assignees: Vec::new(),
+            metadata_file: None,
+            only: false,
+            downstack: false,
+            upstack: false,
+            all: false,
+            select: false,
+        }
+    }
+
+    #[test]
+    fn pr_base_is_nearest_submitted_ancestor() {
+        let dir = tempfile::tempdir().unwrap();
+        let repository = Repository::init(dir.path()).unwrap();
+        let mut ctx = StContext::fresh(StConfig::default(), &repository, "main".to_string());
+        for (parent, child) in [("main", "a"), ("a", "b"), ("b", "c"), ("c", "d")] {
+            ctx.tree.insert(parent, "oid", child).unwrap();
+        }
+        ctx.tree.get_mut("a").unwrap().remote = Some(RemoteMetadata::new(1));
+
+        let branches = ["c".to_string(), "d".to_string()];
+        assert_eq!(SubmitCmd::pr_base(&ctx, "a", &branches).unwrap(), "main");
+        assert_eq!(SubmitCmd::pr_base(&ctx, "c", &branches).unwrap(), "a");
+        assert_eq!(SubmitCmd::pr_base(&ctx, "d", &branches).unwrap(), "c");
+        assert_eq!(SubmitCmd::pr_base(&ctx, "d", &[]).unwrap(), "a");
+    }
+
+    #[test]
+    fn default_pr_metadata_from_commits() {
+        let single = [("Add parser".to_string(), "Parses the input.\n".to_string())];
+        let metadata = SubmitCmd::default_pr_metadata("feat/parser", &single, None);
+        assert_eq!(metadata.title, "Add parser");
+        assert_eq!(metadata.body, "Parses the input.");
+        assert!(metadata.is_draft);
+
+        let several = [
+            ("Add lexer".to_string(), "Tokenizes.".to_string()),
+            ("Fix typo".to_string(), String::new()),
+            ("Add parser".to_string(), "Parses.".to_string()),
+        ];
+        let metadata =
+            SubmitCmd::default_pr_metadata("feat/parser", &several, Some("## Checklist\n"));
+        assert_eq!(metadata.title, "feat/parser");
+        assert_eq!(metadata.body, "Tokenizes.\n\nParses.\n\n## Checklist");
+
+        let metadata = SubmitCmd::default_pr_metadata("empty", &[], Some("## Checklist"));
+        assert_eq!(metadata.title, "empty");
+        assert_eq!(metadata.body, "## Checklist");
+    }
+
+    #[tokio::test]
+    async fn submits_from_fork() {
+        let dir = tempfile::tempdir().unwrap();
+        let (upstream, fork, work) = (
+            dir.path().join("upstream.git"),
+            dir.path().join("fork.git"),
+            dir.path().join("work"),
+        );
+        git(
+            dir.path(),
+            &["init", "--bare", "-b", "main", "upstream.git"],
+        );
+        git(dir.path(), &["init", "--bare", "-b", "main", "fork.git"]);
+        git(dir.path(), &["init", "-b", "main", "work"]);
+        git(
+            &work,
+            &[
+                "remote",
+                "add",
+                "origin",
+                "https://github.com/owner/repo.git",
+            ],
+        );
+        git(
+            &work,
+            &[
+                "config",
+                "remote.origin.pushurl",
+                upstream.to_str().unwrap(),
+            ],
+        );
+        git(
+            &work,
+            &[
+                "remote",
+                "add",
+                "fork",
+                "https://github.com/contributor/repo.git",
+            ],
+        );
+        git(
+            &work,
+            &["config", "remote.fork.pushurl", fork.to_str().unwrap()],
+        );
+
+        // Stack `b` on top of `a` on top of `main`.
+        git(&work, &["commit", "--allow-empty", "-m", "base"]);
+        git(&work, &["checkout", "-b", "a"]);
+        git(&work, &["commit", "--allow-empty", "-m", "Add a"]);
+        git(&work, &["checkout", "-b", "b"]);
+        git(&work, &["commit", "--allow-empty", "-m", "Add b"]);
+
+        let repository = Repository::open(&work).unwrap();
+        let cfg = StConfig {
+            push_remote: Some("fork".to_string()),
+            ..Default::default()
+        };
+        let mut ctx = StContext::fresh(cfg, &repository, "main".to_string());
+        ctx.tree
+            .insert("main", &git(&work, &["rev-parse", "main"]), "a")
+            .unwrap();
+        ctx.tree
+            .insert("a", &git(&work, &["rev-parse", "a"]), "b")
+            .unwrap();
+        let fork_repo = Some(("contributor".to_string(), "repo".to_string()));
+
+        let mock = MockGitHub::start(|request| match request.method.as_str() {
+            "GET" => (200, json!([])),
+            "POST" => (201, pull(1, "a")),
+            _ => (404, json!({ "message": "Not Found" })),
+        })
+        .await;
+        let branches = ["a".to_string(), "b".to_string()];
+        fill()
+            .submit_stack(
+                &mut ctx,
+                &mock.client(),
+                &branches,
+                fork_repo.as_ref(),
+                None,
+            )
+            .await
+            .unwrap();
+
+        // Both branches are pushed to the fork, and nothing to the upstream repository.
+        for branch in ["a", "b"] {
+            assert_eq!(
+                git(&fork, &["rev-parse", branch]),
+                git(&work, &["rev-parse", branch])
+            );
+        }
+        assert!(git(&upstream, &["branch", "--list"]).is_empty());
+
+        // Only `a` is opened upstream, with its head qualified by the owner of the fork.
+        let requests = mock.requests();
+        let created = requests
+            .iter()
+            .filter(|r| r.method == "POST")
+            .collect::<Vec<_>>();
+        assert_eq!(created.len(), 1);
+        assert_eq!(created[0].path, "/repos/owner/repo/pulls");
+        assert_eq!(created[0].body["head"], "contributor:a");
+        assert_eq!(created[0].body["base"], "main");
+        assert_eq!(
+            ctx.tree
+                .get("a")
+                .unwrap()
+                .remote
+                .as_ref()
+                .unwrap()
+                .pr_number,
+            1
+        );
+        assert!(ctx.tree.get("b").unwrap().remote.is_none());
+    }
+
+    #[tokio::test]
+    async fn requests_review_from_user_sharing_a_team_slug() {
+        let mock = MockGitHub::start(|request| match request.method.as_str() {
+            "GET" => (200, json!([])),
+            _ => (201, pull(1, "a")),
+        })
+        .await;
+        let mut pr = pull(1, "a");
+        pr["requested_teams"] = json!([{
+            "name": "Core",
+            "slug": "core",
+            "privacy": "closed",
+            "permission": "pull",
+            "members_url": "https://api.github.com/teams/1/members",
+            "repositories_url": "https://api.github.com/teams/1/repos",
+        }]);
+        let pr = serde_json::from_value(pr).unwrap();
+        let fields = PullRequestFields {
+            reviewers: Some(vec!["core".to_string(), "org/core".to_string()]),
+            ..Default::default()
+        };
+
+        let client = mock.client();
+        SubmitCmd::add_participants(&client.pulls(), &client.issues(), &pr, &fields, None)
+            .await
+            .unwrap();
+
+        // The `core` team is already requested, but the `core` user is not.
+        let requests = mock.requests();
+        let requested = requests
+            .iter()
+            .find(|r| r.path == "/repos/owner/repo/pulls/1/requested_reviewers")
+            .unwrap();
+        assert_eq!(requested.body["reviewers"], json!(["core"]));
+        assert_eq!(requested.body["team_reviewers"], json!([]));
+    }
+
+    #[tokio::test]
+    async fn finds_open_pull_request_by_head_branch() {
+        let mock = MockGitHub::start(|request| match request.path.as_str() {
+            "/repos/owner/repo/pulls" => (200, json!([pull(7, "feat")])),
+            _ => (404, json!({ "message": "Not Found" })),
+        })
+        .await;
+
+        let client = mock.client();
+        let found = SubmitCmd::find_open_pr(&client.pulls(), "contributor", "feat")
+            .await
+            .unwrap();
+        assert_eq!(found, Some(7));
+
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].query.contains("state=open"));
+        assert!(requests[0].query.contains("head=contributor%3Afeat"));
+    }
+
+    #[tokio::test]
+    async fn finds_own_stack_comment_by_marker() {
+        let marked = format!("{}\nStack", COMMENT_MARKER);
+        let comments = json!([
+            comment(1, "reviewer", &format!("> {}", marked)),
+            comment(2, "me", "Thanks!"),
+            comment(3, "me", &marked),
+            comment(4, "me", &marked),
+        ]);
+        let mock = MockGitHub::start(move |_| (200, comments.clone())).await;
+
+        let client = mock.client();
+        let found = SubmitCmd::find_stack_comment(&client.issues(), 1, "me")
+            .await
+            .unwrap();
+        assert_eq!(found, Some(3));
+        let found = SubmitCmd::find_stack_comment(&client.issues(), 1, "someone")
+            .await
+            .unwrap();
+        assert_eq!(found, None);
+    }
+
+    #[tokio::test]
+    async fn recreates_deleted_stack_comment() {
+        let dir = tempfile::tempdir().unwrap();
+        let repository = Repository::init(dir.path()).unwrap();
+        repository
+            .remote("origin", "https://github.com/owner/repo.git")
+            .unwrap();
+        let mut ctx = StContext::fresh(StConfig::default(), &repository, "main".to_string());
+        ctx.tree.insert("main", "oid", "a").unwrap();
+        ctx.tree.get_mut("a").unwrap().remote = Some(RemoteMetadata {
+            comment_id: Some(99),
+            ..RemoteMetadata::new(1)
+        });
+
+        let mock =
+            MockGitHub::start(
+                |request| match (request.method.as_str(), request.path.as_str()) {
+                    ("POST", "/graphql") => (200, pull_status(1, "abc", None)),
+                    ("POST", "/repos/owner/repo/issues/1/comments") => (
+                        201,
+                        comment(100, "me", request.body["body"].as_str().unwrap_or_default()),
+                    ),
+                    ("GET", "/user") => (200, user("me")),
+                    _ => (404, json!({ "message": "Not Found" })),
+                },
+            )
+            .await;
+
+        fill()
+            .update_pr_comments(&mut ctx, &mock.client(), &["a".to_string()], None)
+            .await
+            .unwrap();
+
+        // The stored comment was deleted on GitHub, so a new one is posted and recorded.
+        let requests = mock.requests();
+        let routes = requests
+            .iter()
+            .map(|r| format!("{} {}", r.method, r.path))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            routes,
+            [
+                "POST /graphql",
+                "POST /repos/owner/repo/issues/comments/99",
+                "POST /repos/owner/repo/issues/1/comments",
+            ]
+        );
+        assert!(requests[2].body["body"]
+            .as_str()
+            .unwrap()
+            .starts_with(COMMENT_MARKER));
+        let remote = ctx.tree.get("a").unwrap().remote.clone().unwrap();
+        assert_eq!(remote.comment_id, Some(100));
+    }
+}
@@ -4,10 +4,10 @@ use crate::{
     ctx::StContext,
     errors::{StError, StResult},
     git::RepositoryExt,
+    github::GitHubClient,
 };
 use clap::Args;
 use nu_ansi_term::Color;
-use octocrab::{pulls::PullRequestHandler, Octocrab};
 
 /// CLI arguments for the `sync` subcommand.
 #[derive(Debug, Clone, Eq, PartialEq, Args)]
@@ -17,23 +17,23 @@ impl SyncCmd {
     /// Run the `sync` subcommand.
     pub async fn run(self, mut ctx: StContext<'_>) -> StResult<()> {
         // Establish the GitHub API client.
-        let gh_client = Octocrab::builder()
-            .personal_token(ctx.cfg.github_token.clone())
-            .build()?;
-        let (owner, repo) = ctx.owner_and_repository()?;
-        let mut pulls = gh_client.pulls(&owner, &repo);
+        let gh_client = ctx.github_client()?;
 
         // Perform pre-flight checks.
-        self.pre_flight(&mut ctx, &mut pulls).await?;
+        self.pre_flight(&mut ctx, &gh_client).await?;
 
         // Resolve all branches in the stack tree after the deletions have been applied.
         let branches = ctx.tree.branches()?;
-        let branches_without_trunk = branches.iter().skip(1).cloned().collect::<Vec<_>>();
+        let branches_without_trunk = branches
+            .iter()
+            .filter(|b| !ctx.tree.is_trunk(b))
+            .cloned()
+            .collect::<Vec<_>>();
 
         // Pull all of the latest changes from GitHub.
         println!(
             "\n🐙 Pulling latest changes from remote `{}`...",
-            Color::Blue.paint("origin")
+            Color::Blue.paint(ctx.remote_name())
         );
         self.pull_changes(&mut ctx, branches.as_slice()).await?;
 
@@ -51,14 +51,14 @@ impl SyncCmd {
     /// Steps:
     /// 1. Check if the working tree is clean.
     /// 2. Check if any PRs have been closed, and offer to delete branches before pulling latest.
-    async fn pre_flight(
-        &self,
-        ctx: &mut StContext<'_>,
-        pulls: &mut PullRequestHandler<'_>,
-    ) -> StResult<()> {
+    async fn pre_flight(&self, ctx: &mut StContext<'_>, gh_client: &GitHubClient) -> StResult<()> {
         // Resolve the active stack.
         let branches = ctx.tree.branches()?;
-        let branches_without_trunk = branches.iter().skip(1).cloned().collect::<Vec<_>>();
+        let branches_without_trunk = branches
+            .iter()
+            .filter(|b| !ctx.tree.is_trunk(b))
+            .cloned()
+            .collect::<Vec<_>>();
 
         // Return early if the stack is not restacked or the current working tree is dirty.
         if !ctx.repository.is_working_tree_clean()? {
@@ -67,7 +67,7 @@ impl SyncCmd {
 
         // Check if any PRs have been closed, and offer to delete them before pulling latest
         // changes from GitHub.
-        ctx.delete_closed_branches(branches_without_trunk.as_slice(), pulls)
+        ctx.delete_closed_branches(branches_without_trunk.as_slice(), gh_client)
             .await?;
 
         Ok(())
@@ -81,11 +81,29 @@ impl SyncCmd {
                 .tree
                 .get(branch)
                 .ok_or_else(|| StError::BranchNotTracked(branch.clone()))?;
-            if tracked_branch.remote.is_none() && branch != &ctx.tree.trunk_name {
+            if tracked_branch.remote.is_none() && !ctx.tree.is_trunk(branch) {
                 continue;
             }
 
-            if let Err(e) = ctx.repository.pull_branch(branch, "origin") {
+            // If the branch is checked out in another worktree, skip it.
+            if let Some(path) = ctx.repository.checked_out_elsewhere(branch)? {
+                println!(
+                    "Branch `{}` is checked out in the worktree at `{}`. Skipping pull.",
+                    Color::Green.paint(branch),
+                    path.display()
+                );
+                continue;
+            }
+
+            // Trunk branches live in the upstream repository, while stacked branches are pushed
+            // to the push remote.
+            let remote = if ctx.tree.is_trunk(branch) {
+                ctx.remote_name()
+            } else {
+                ctx.push_remote_name()
+            };
+
+            if let Err(e) = ctx.repository.pull_branch(branch, remote) {
                 eprintln!("{}\n\n", e);
 
                 let message = format!(
@@ -99,8 +117,7 @@ impl SyncCmd {
                 .prompt()?;
 
                 if option.contains("Overwrite") {
-                    ctx.repository
-                        .set_target_to_upstream_ref(branch, "origin")?;
+                    ctx.repository.set_target_to_upstream_ref(branch, remote)?;
                     println!(
                         "Successfully overwrote local branch `{}` with remote version.",
                         Color::Green.paint(branch)
@@ -130,8 +147,11 @@ impl SyncCmd {
                     .clone();
 
                 let mut num_conflicts = 0;
-                if ctx.restack_branch(branch, &parent_name).is_err() {
-                    ctx.repository.abort_rebase()?;
+                if let Err(e) = ctx.restack_branch(branch, &parent_name) {
+                    // Failed restacks in other worktrees have already been aborted.
+                    if !matches!(e, StError::WorktreeRestackFailed(..)) {
+                        ctx.repository.abort_rebase()?;
+                    }
                     println!(
                         "Failed to restack branch `{}` onto `{}`.",
                         Color::Green.paint(branch),
@@ -0,0 +1,214 @@
+//! Discovery of pull request templates.
+//!
+//! Templates are looked up in every location that GitHub supports, relative to the root of the
+//! repository:
+//! - A single template, `pull_request_template.md`, in `.github/`, the root, or `docs/`.
+//! - Multiple templates, as Markdown files within a `PULL_REQUEST_TEMPLATE/` directory in any of
+//!   the same locations.
+//!
+//! File and directory names are matched case-insensitively, as on GitHub.
+
+use crate::errors::{StError, StResult};
+use nu_ansi_term::Color;
+use std::{
+    fmt::{self, Display},
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// The directories that GitHub searches for pull request templates, in order of precedence.
+const TEMPLATE_DIRS: &[&str] = &[".github", "", "docs"];
+
+/// The file name of a single template without its extension, and the name of the directory
+/// holding multiple templates, both in lowercase.
+const TEMPLATE_NAME: &str = "pull_request_template";
+
+/// A pull request template found within the repository.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PullRequestTemplate {
+    /// The name of the template, i.e. its file name without the extension.
+    pub name: String,
+    /// The path of the template, relative to the root of the repository.
+    pub path: PathBuf,
+    /// The contents of the template.
+    pub body: String,
+}
+
+impl Display for PullRequestTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.name, self.path.display())
+    }
+}
+
+impl PullRequestTemplate {
+    /// Discovers all pull request templates within the repository rooted at `root`.
+    ///
+    /// ## Takes
+    /// - `root` - The root of the repository's working tree.
+    ///
+    /// ## Returns
+    /// - `Vec<PullRequestTemplate>` - The templates, single templates first, in order of precedence.
+    pub fn discover(root: &Path) -> Vec<Self> {
+        let mut singles = Vec::new();
+        let mut multiples = Vec::new();
+
+        for dir in TEMPLATE_DIRS {
+            for (entry_name, path) in read_dir_sorted(&root.join(dir)) {
+                let lower = entry_name.to_lowercase();
+                if path.is_file() && is_template_file(&lower, TEMPLATE_NAME) {
+                    singles.extend(Self::read(root, &path));
+                } else if path.is_dir() && lower == TEMPLATE_NAME {
+                    multiples.extend(
+                        read_dir_sorted(&path)
+                            .into_iter()
+                            .filter(|(name, path)| {
+                                path.is_file() && name.to_lowercase().ends_with(".md")
+                            })
+                            .filter_map(|(_, path)| Self::read(root, &path)),
+                    );
+                }
+            }
+        }
+
+        singles.extend(multiples);
+        singles
+    }
+
+    /// Selects the template to prefill a pull request description with.
+    ///
+    /// ## Takes
+    /// - `templates` - The templates found within the repository.
+    /// - `name` - The name of the template requested on the command line, if any.
+    /// - `interactive` - Whether to prompt the user if there are several templates. Otherwise, the
+    ///   template with the highest precedence is used.
+    ///
+    /// ## Returns
+    /// - `Ok(Some(PullRequestTemplate))` - The requested template, the only template, or the one
+    ///   picked by the user.
+    /// - `Ok(None)` - If there are no templates, or the user picked none.
+    /// - `Err(StError::PullRequestTemplateNotFound)` - If the requested template does not exist.
+    pub fn select(
+        templates: &[Self],
+        name: Option<&str>,
+        interactive: bool,
+    ) -> StResult<Option<Self>> {
+        if let Some(name) = name {
+            return templates
+                .iter()
+                .find(|t| t.name.eq_ignore_ascii_case(name))
+                .cloned()
+                .map(Some)
+                .ok_or_else(|| StError::PullRequestTemplateNotFound(name.to_string()));
+        }
+
+        match templates {
+            [] => Ok(None),
+            [template] => Ok(Some(template.clone())),
+            [template, ..] if !interactive => Ok(Some(template.clone())),
+            _ => {
+                let mut options = templates
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>();
+                options.push(String::from("No template"));
+                let choice = inquire::Select::new(
+                    &format!("Select a {} template:", Color::Blue.paint("pull request")),
+                    options,
+                )
+                .raw_prompt()?;
+                Ok(templates.get(choice.index).cloned())
+            }
+        }
+    }
+
+    /// Reads the template at `path`, skipping files that cannot be read.
+    fn read(root: &Path, path: &Path) -> Option<Self> {
+        let body = fs::read_to_string(path).ok()?;
+        let name = path.file_stem()?.to_string_lossy().to_string();
+        let path = path.strip_prefix(root).unwrap_or(path).to_path_buf();
+        Some(Self { name, path, body })
+    }
+}
+
+/// Returns whether the lowercase file name is `<stem>.md`, `<stem>.txt`, or `<stem>`.
+fn is_template_file(lower_name: &str, stem: &str) -> bool {
+    lower_name
+        .strip_prefix(stem)
+        .is_some_and(|ext| matches!(ext, "" | ".md" | ".txt"))
+}
+
+/// Lists the entries of a directory, sorted by name. Missing directories have no entries.
+fn read_dir_sorted(dir: &Path) -> Vec<(String, PathBuf)> {
+    let mut entries = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .map(|e| (e.file_name().to_string_lossy().to_string(), e.path()))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    entries.sort();
+    entries
+}
+
+#[cfg(test)]
+mod test {
+    use super::PullRequestTemplate;
+    use crate::errors::StError;
+    use std::{fs, path::PathBuf};
+
+    #[test]
+    fn discovers_templates_in_all_locations() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for (path, body) in [
+            (".github/PULL_REQUEST_TEMPLATE.md", "github"),
+            ("docs/pull_request_template.md", "docs"),
+            (".github/PULL_REQUEST_TEMPLATE/feature.md", "feature"),
+            (".github/PULL_REQUEST_TEMPLATE/Bugfix.md", "bugfix"),
+            (".github/PULL_REQUEST_TEMPLATE/notes.txt", "ignored"),
+            ("pull_request_template/root.md", "root"),
+            ("README.md", "ignored"),
+        ] {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, body).unwrap();
+        }
+
+        let templates = PullRequestTemplate::discover(root)
+            .into_iter()
+            .map(|t| (t.name, t.path, t.body))
+            .collect::<Vec<_>>();
+        #[rustfmt::skip]
+        let expected = [
+            ("PULL_REQUEST_TEMPLATE", ".github/PULL_REQUEST_TEMPLATE.md", "github"),
+            ("pull_request_template", "docs/pull_request_template.md", "docs"),
+            ("Bugfix", ".github/PULL_REQUEST_TEMPLATE/Bugfix.md", "bugfix"),
+            ("feature", ".github/PULL_REQUEST_TEMPLATE/feature.md", "feature"),
+            ("root", "pull_request_template/root.md", "root"),
+        ]
+        .map(|(name, path, body)| (name.to_string(), PathBuf::from(path), body.to_string()));
+        assert_eq!(templates, expected);
+    }
+
+    #[test]
+    fn selects_templates_by_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let template_dir = dir.path().join(".github/pull_request_template");
+        fs::create_dir_all(&template_dir).unwrap();
+        fs::write(template_dir.join("feature.md"), "feature").unwrap();
+        fs::write(template_dir.join("bugfix.md"), "bugfix").unwrap();
+        let templates = PullRequestTemplate::discover(dir.path());
+
+        let selected = PullRequestTemplate::select(&templates, Some("Feature"), true).unwrap();
+        assert_eq!(selected.unwrap().body, "feature");
+        assert!(matches!(
+            PullRequestTemplate::select(&templates, Some("missing"), true),
+            Err(StError::PullRequestTemplateNotFound(name)) if name == "missing"
+        ));
+        assert_eq!(PullRequestTemplate::select(&[], None, true).unwrap(), None);
+
+        let selected = PullRequestTemplate::select(&templates, None, false).unwrap();
+        assert_eq!(selected.unwrap().name, "bugfix");
+    }
+}
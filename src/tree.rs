@@ -1,10 +1,22 @@
 //! Structured, [Serialize] + [Deserialize] representation of a stack of branches.
 
-use crate::errors::{StError, StResult};
-use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use crate::{
+    errors::{StError, StResult},
+    github::{
+        status::{CheckStatus, Mergeability, ReviewStatus},
+        PullRequestState, PullRequestStatus,
+    },
+};
+use serde::{Deserialize, Serialize, Serializer};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    time::{SystemTime, UNIX_EPOCH},
+};
 
-/// A simple n-nary tree of branches, with bidirectional references.
+/// A simple n-nary forest of branches, with bidirectional references.
+///
+/// Every branch without a parent is a trunk. The trunk selected when the repository was set up is
+/// the default trunk, and additional trunks (e.g. long-lived release branches) may be added.
 ///
 /// By itself, [StackTree] has no context of its relationship with the local repository. For this functionality,
 /// [StContext] holds onto both the [StackTree] and the [Repository] to make informed decisions about the tree.
@@ -14,9 +26,10 @@ use std::collections::{HashMap, HashSet};
 #[derive(Default, Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct StackTree {
-    /// The name of the trunk branch.
+    /// The name of the default trunk branch.
     pub trunk_name: String,
     /// A map of branch names to [TrackedBranch]es.
+    #[serde(serialize_with = "serialize_sorted_map")]
     pub branches: HashMap<String, TrackedBranch>,
 }
 
@@ -34,6 +47,87 @@ impl StackTree {
         }
     }
 
+    /// Returns the names of all trunk branches, with the default trunk first.
+    pub fn trunks(&self) -> Vec<&str> {
+        let mut additional = self
+            .branches
+            .values()
+            .filter(|b| b.parent.is_none() && b.name != self.trunk_name)
+            .map(|b| b.name.as_str())
+            .collect::<Vec<_>>();
+        additional.sort();
+
+        std::iter::once(self.trunk_name.as_str())
+            .chain(additional)
+            .collect()
+    }
+
+    /// Returns whether or not the branch is a tracked trunk branch.
+    pub fn is_trunk(&self, branch_name: &str) -> bool {
+        self.get(branch_name).is_some_and(|b| b.parent.is_none())
+    }
+
+    /// Returns the name of the trunk branch that the given branch descends from.
+    ///
+    /// ## Takes
+    /// - `branch_name` - The name of the branch.
+    ///
+    /// ## Returns
+    /// - `Ok(trunk_name)` - The name of the trunk that the branch descends from.
+    /// - `Err(_)` - The branch, or one of its ancestors, is not tracked.
+    pub fn trunk_of(&self, branch_name: &str) -> StResult<&str> {
+        let mut current = self
+            .get(branch_name)
+            .ok_or_else(|| StError::BranchNotTracked(branch_name.to_string()))?;
+        while let Some(ref parent) = current.parent {
+            current = self
+                .get(parent)
+                .ok_or_else(|| StError::BranchNotTracked(parent.to_string()))?;
+        }
+        Ok(current.name.as_str())
+    }
+
+    /// Adds an additional trunk branch to the stack graph.
+    ///
+    /// ## Takes
+    /// - `trunk_name` - The name of the new trunk branch.
+    ///
+    /// ## Returns
+    /// - `Ok(())` if the trunk was successfully added.
+    /// - `Err(_)` if the branch is already tracked.
+    pub fn add_trunk(&mut self, trunk_name: &str) -> StResult<()> {
+        if self.branches.contains_key(trunk_name) {
+            return Err(StError::BranchAlreadyTracked(trunk_name.to_string()));
+        }
+
+        self.branches.insert(
+            trunk_name.to_string(),
+            TrackedBranch::new(trunk_name.to_string(), None, None),
+        );
+        Ok(())
+    }
+
+    /// Removes an additional trunk branch from the stack graph.
+    ///
+    /// ## Takes
+    /// - `trunk_name` - The name of the trunk branch to remove.
+    ///
+    /// ## Returns
+    /// - `Ok(())` if the trunk was successfully removed.
+    /// - `Err(_)` if the branch is not an additional trunk, or still has branches stacked on it.
+    pub fn remove_trunk(&mut self, trunk_name: &str) -> StResult<()> {
+        if trunk_name == self.trunk_name {
+            return Err(StError::CannotRemoveDefaultTrunk);
+        } else if !self.is_trunk(trunk_name) {
+            return Err(StError::NotATrunk(trunk_name.to_string()));
+        } else if self.get(trunk_name).is_some_and(|b| !b.children.is_empty()) {
+            return Err(StError::TrunkHasChildren(trunk_name.to_string()));
+        }
+
+        self.branches.remove(trunk_name);
+        Ok(())
+    }
+
     /// Gets a branch by name from the stack graph.
     ///
     /// ## Takes
@@ -58,6 +152,29 @@ impl StackTree {
         self.branches.get_mut(branch_name)
     }
 
+    /// Collects the cached state of the pull requests in the stack graph.
+    ///
+    /// ## Returns
+    /// - `BTreeMap<String, CachedPullRequest>` - The cached state, keyed by pull request number.
+    pub fn pull_request_cache(&self) -> BTreeMap<String, CachedPullRequest> {
+        self.branches
+            .values()
+            .filter_map(|b| b.remote.as_ref())
+            .filter_map(|r| Some((r.pr_number.to_string(), r.cache.clone()?)))
+            .collect()
+    }
+
+    /// Attaches the cached state of pull requests to the branches that they were opened for.
+    ///
+    /// ## Takes
+    /// - `cache` - The cached state, keyed by pull request number.
+    pub fn attach_pull_request_cache(&mut self, cache: &BTreeMap<String, CachedPullRequest>) {
+        self.branches
+            .values_mut()
+            .filter_map(|b| b.remote.as_mut())
+            .for_each(|r| r.cache = cache.get(&r.pr_number.to_string()).cloned());
+    }
+
     /// Adds a child branch to the passed parent branch, if it exists.
     ///
     /// ## Takes
@@ -103,6 +220,11 @@ impl StackTree {
     /// - `Some(branch)` - The deleted branch.
     /// - `None` - The branch by the name of `branch` was not found.
     pub fn delete(&mut self, branch_name: &str) -> StResult<TrackedBranch> {
+        // Trunk branches are removed with `remove_trunk`, so that their children are never orphaned.
+        if self.is_trunk(branch_name) {
+            return Err(StError::CannotDeleteTrunkBranch);
+        }
+
         // Remove the branch from the stack tree.
         let branch = self
             .branches
@@ -139,16 +261,168 @@ impl StackTree {
         Ok(branch)
     }
 
-    /// Returns a vector of branch names in the stack graph. The vector is filled recursively, meaning that children are
-    /// guaranteed to be listed after their parents.
+    /// Performs a three-way merge of two divergent versions of a [StackTree], branch by branch.
+    ///
+    /// For every branch, a side that did not change the branch relative to `base` yields to the
+    /// side that did. If both sides changed the same branch in different ways, `ours` wins and
+    /// the branch is reported as a conflict. The children of each branch are re-derived from the
+    /// merged parent links.
+    ///
+    /// ## Takes
+    /// - `base` - The common ancestor of both trees.
+    /// - `ours` - The local version of the tree.
+    /// - `theirs` - The remote version of the tree.
+    ///
+    /// ## Returns
+    /// - `(StackTree, Vec<String>)` - The merged tree, and the names of conflicting branches.
+    pub fn merge(base: &Self, ours: &Self, theirs: &Self) -> (Self, Vec<String>) {
+        // Children are derived from the parent links, so they are not compared.
+        let normalized = |tree: &Self, name: &str| {
+            tree.branches.get(name).map(|b| TrackedBranch {
+                children: HashSet::new(),
+                ..b.clone()
+            })
+        };
+
+        // The cached pull request state is refreshed independently by every clone, so it does not
+        // take part in the comparison.
+        let without_cache = |branch: &Option<TrackedBranch>| {
+            branch.clone().map(|mut b| {
+                if let Some(remote) = b.remote.as_mut() {
+                    remote.cache = None;
+                }
+                b
+            })
+        };
+
+        let names = base
+            .branches
+            .keys()
+            .chain(ours.branches.keys())
+            .chain(theirs.branches.keys())
+            .collect::<HashSet<_>>();
+
+        let mut conflicts = Vec::new();
+        let mut branches = HashMap::new();
+        for name in names {
+            let (b, o, t) = (
+                normalized(base, name),
+                normalized(ours, name),
+                normalized(theirs, name),
+            );
+
+            let (bc, oc, tc) = (without_cache(&b), without_cache(&o), without_cache(&t));
+            let merged = if oc == tc || tc == bc {
+                o
+            } else if oc == bc {
+                t
+            } else {
+                conflicts.push(name.clone());
+                o
+            };
+
+            if let Some(branch) = merged {
+                branches.insert(name.clone(), branch);
+            }
+        }
+
+        // Resurrect parents that were deleted on one side while a child was changed on the other,
+        // so that no branch is left dangling.
+        loop {
+            let missing = branches
+                .values()
+                .filter_map(|b| b.parent.clone())
+                .filter(|p| !branches.contains_key(p))
+                .collect::<HashSet<_>>();
+            if missing.is_empty() {
+                break;
+            }
+
+            for name in missing {
+                let parent = normalized(ours, &name)
+                    .or_else(|| normalized(theirs, &name))
+                    .or_else(|| normalized(base, &name));
+                match parent {
+                    Some(parent) => {
+                        branches.insert(name, parent);
+                    }
+                    None => branches
+                        .values_mut()
+                        .filter(|b| b.parent.as_ref() == Some(&name))
+                        .for_each(|b| b.parent = Some(ours.trunk_name.clone())),
+                }
+            }
+        }
+
+        // Re-derive the children of each branch from the merged parent links.
+        let links = branches
+            .values()
+            .filter_map(|b| Some((b.parent.clone()?, b.name.clone())))
+            .collect::<Vec<_>>();
+        for (parent, child) in links {
+            if let Some(parent) = branches.get_mut(&parent) {
+                parent.children.insert(child);
+            }
+        }
+
+        conflicts.sort();
+        let tree = Self {
+            trunk_name: ours.trunk_name.clone(),
+            branches,
+        };
+        (tree, conflicts)
+    }
+
+    /// Returns a vector of branch names in the stack graph, starting with the default trunk. The vector is filled
+    /// recursively, meaning that children are guaranteed to be listed after their parents.
     pub fn branches(&self) -> StResult<Vec<String>> {
         let mut branch_names = Vec::new();
-        self.fill_branches(&self.trunk_name, &mut branch_names)?;
+        self.trunks()
+            .into_iter()
+            .try_for_each(|trunk| self.fill_branches(trunk, &mut branch_names))?;
         Ok(branch_names)
     }
 
-    /// Fills a vector with the trunk branch and its children. The resulting vector is filled recursively, meaning that
-    /// children are guaranteed to be listed after their parents.
+    /// Returns the names of the ancestors of a branch, from its trunk down to its parent.
+    ///
+    /// ## Takes
+    /// - `branch_name` - The name of the branch.
+    ///
+    /// ## Returns
+    /// - `Ok(ancestors)` - The ancestors of the branch. Empty if the branch is a trunk.
+    /// - `Err(_)` - The branch, or one of its ancestors, is not tracked.
+    pub fn ancestors(&self, branch_name: &str) -> StResult<Vec<String>> {
+        let mut ancestors = Vec::new();
+        let mut current = self
+            .get(branch_name)
+            .ok_or_else(|| StError::BranchNotTracked(branch_name.to_string()))?;
+        while let Some(ref parent) = current.parent {
+            ancestors.push(parent.clone());
+            current = self
+                .get(parent)
+                .ok_or_else(|| StError::BranchNotTracked(parent.to_string()))?;
+        }
+        ancestors.reverse();
+        Ok(ancestors)
+    }
+
+    /// Returns the names of a branch and every branch stacked on top of it, including all forks. Children are
+    /// guaranteed to be listed after their parents.
+    ///
+    /// ## Takes
+    /// - `branch_name` - The name of the branch at the root of the subtree.
+    ///
+    /// ## Returns
+    /// - `Ok(subtree)` - The branches of the subtree.
+    /// - `Err(_)` - One of the branches is not tracked.
+    pub fn subtree(&self, branch_name: &str) -> StResult<Vec<String>> {
+        let mut branch_names = Vec::new();
+        self.fill_branches(branch_name, &mut branch_names)?;
+        Ok(branch_names)
+    }
+
+    /// Fills a vector with a trunk branch and its children. The resulting vector is filled recursively, meaning that
+    /// children are guaranteed to be listed after their parents. Siblings are listed in alphabetical order.
     fn fill_branches(&self, name: &str, branch_names: &mut Vec<String>) -> StResult<()> {
         let current = self
             .branches
@@ -156,9 +430,10 @@ impl StackTree {
             .ok_or_else(|| StError::BranchNotTracked(name.to_string()))?;
 
         branch_names.push(current.name.clone());
-        current
-            .children
-            .iter()
+        let mut children = current.children.iter().collect::<Vec<_>>();
+        children.sort();
+        children
+            .into_iter()
             .try_for_each(|child| self.fill_branches(child, branch_names))
     }
 }
@@ -176,10 +451,11 @@ pub struct TrackedBranch {
     pub parent_oid_cache: Option<String>,
     /// The index of the parent branch in the stack graph.
     ///
-    /// [None] if the branch is trunk.
+    /// [None] if the branch is a trunk.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub parent: Option<String>,
     /// The index of the child branches within the stack graph.
+    #[serde(serialize_with = "serialize_sorted_set")]
     pub children: HashSet<String>,
     /// The [RemoteMetadata] for the branch.
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -205,7 +481,7 @@ impl TrackedBranch {
 }
 
 /// Remote metadata for a branch that is tracked by `st`.
-#[derive(Default, Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Default, Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct RemoteMetadata {
     /// The number of the pull request on GitHub associated with the branch.
@@ -215,6 +491,12 @@ pub struct RemoteMetadata {
     /// This is used to update the comment with the latest stack status each time the stack
     /// is submitted.
     pub(crate) comment_id: Option<u64>,
+    /// The state of the pull request as of the last time it was fetched from GitHub.
+    ///
+    /// This is used to show the state of pull requests without a round trip to GitHub. It is
+    /// kept in a file of its own, so that it is never recorded in the shared metadata.
+    #[serde(skip)]
+    pub(crate) cache: Option<CachedPullRequest>,
 }
 
 impl RemoteMetadata {
@@ -223,6 +505,234 @@ impl RemoteMetadata {
         Self {
             pr_number,
             comment_id: None,
+            cache: None,
+        }
+    }
+}
+
+/// Returns the current time, in seconds since the Unix epoch.
+pub fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// The last-fetched state of a pull request.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct CachedPullRequest {
+    /// The title of the pull request.
+    pub title: String,
+    /// The state of the pull request.
+    pub state: PullRequestState,
+    /// The review decision of the pull request.
+    pub review: ReviewStatus,
+    /// The combined status of the checks of the head commit.
+    pub checks: CheckStatus,
+    /// The names of the failing checks of the head commit.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub failing_checks: Vec<String>,
+    /// Whether the pull request can be merged without conflicts.
+    #[serde(default)]
+    pub mergeable: Mergeability,
+    /// The number of unresolved review conversations, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unresolved_threads: Option<u64>,
+    /// The object ID of the head commit.
+    pub head_sha: String,
+    /// When the state was fetched, in seconds since the Unix epoch.
+    pub fetched_at: u64,
+}
+
+impl CachedPullRequest {
+    /// Creates a [CachedPullRequest] from a freshly fetched [PullRequestStatus].
+    pub fn new(status: &PullRequestStatus, fetched_at: u64) -> Self {
+        Self {
+            title: status.title.clone(),
+            state: status.state,
+            review: status.review,
+            checks: status.checks,
+            failing_checks: status.failing_checks.clone(),
+            mergeable: status.mergeable,
+            unresolved_threads: status.unresolved_threads,
+            head_sha: status.head_oid.clone(),
+            fetched_at,
+        }
+    }
+
+    /// Returns whether the state was fetched at most `max_age` seconds before `now`.
+    pub fn is_fresh(&self, now: u64, max_age: u64) -> bool {
+        now.saturating_sub(self.fetched_at) <= max_age
+    }
+
+    /// Returns how long before `now` the state was fetched, e.g. `5m ago`.
+    pub fn age(&self, now: u64) -> String {
+        match now.saturating_sub(self.fetched_at) {
+            age if age < 60 => "just now".to_string(),
+            age if age < 60 * 60 => format!("{}m ago", age / 60),
+            age if age < 24 * 60 * 60 => format!("{}h ago", age / (60 * 60)),
+            age => format!("{}d ago", age / (24 * 60 * 60)),
+        }
+    }
+
+    /// Returns a one-line summary of the state and its age, used to annotate branches.
+    pub fn annotation(&self, number: u64, now: u64) -> String {
+        let age = self.age(now);
+        match self.state {
+            PullRequestState::Open | PullRequestState::Draft => format!(
+                "#{} {} · {} · {}, {}",
+                number, self.state, self.review, self.checks, age
+            ),
+            PullRequestState::Merged | PullRequestState::Closed => {
+                format!("#{} {}, {}", number, self.state, age)
+            }
         }
     }
 }
+
+/// Serializes a map in key order, so that an unchanged [StackTree] always serializes identically.
+fn serialize_sorted_map<S: Serializer, V: Serialize>(
+    map: &HashMap<String, V>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    map.iter().collect::<BTreeMap<_, _>>().serialize(serializer)
+}
+
+/// Serializes a set in order, so that an unchanged [StackTree] always serializes identically.
+fn serialize_sorted_set<S: Serializer>(
+    set: &HashSet<String>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    set.iter().collect::<BTreeSet<_>>().serialize(serializer)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CachedPullRequest, RemoteMetadata, StackTree};
+    use crate::github::{
+        status::{CheckStatus, Mergeability, ReviewStatus},
+        PullRequestState,
+    };
+
+    fn tree(links: &[(&str, &str)]) -> StackTree {
+        let mut tree = StackTree::new("main".to_string());
+        links
+            .iter()
+            .for_each(|(parent, child)| tree.insert(parent, "oid", child).unwrap());
+        tree
+    }
+
+    #[test]
+    fn branches_spans_every_trunk() {
+        let mut tree = tree(&[("main", "a")]);
+        tree.add_trunk("release/1").unwrap();
+        tree.insert("release/1", "oid", "b").unwrap();
+
+        assert_eq!(tree.trunks(), vec!["main", "release/1"]);
+        assert_eq!(
+            tree.branches().unwrap(),
+            vec!["main", "a", "release/1", "b"]
+        );
+        assert_eq!(tree.trunk_of("b").unwrap(), "release/1");
+        assert!(tree.remove_trunk("release/1").is_err());
+        assert!(tree.remove_trunk("main").is_err());
+    }
+
+    #[test]
+    fn ancestors_and_subtree() {
+        let tree = tree(&[("main", "a"), ("a", "b"), ("a", "c"), ("c", "d")]);
+
+        assert_eq!(tree.ancestors("d").unwrap(), vec!["main", "a", "c"]);
+        assert!(tree.ancestors("main").unwrap().is_empty());
+        assert_eq!(tree.subtree("a").unwrap(), vec!["a", "b", "c", "d"]);
+        assert_eq!(tree.subtree("b").unwrap(), vec!["b"]);
+        assert!(tree.subtree("missing").is_err());
+    }
+
+    #[test]
+    fn merge_takes_changes_from_both_sides() {
+        let base = tree(&[("main", "a")]);
+        let ours = tree(&[("main", "a"), ("a", "b")]);
+        let mut theirs = tree(&[("main", "a"), ("main", "c")]);
+        theirs.get_mut("a").unwrap().remote = Some(RemoteMetadata::new(1));
+
+        let (merged, conflicts) = StackTree::merge(&base, &ours, &theirs);
+        assert!(conflicts.is_empty());
+        assert_eq!(
+            merged.get("a").unwrap().remote.as_ref().unwrap().pr_number,
+            1
+        );
+        assert!(merged.get("a").unwrap().children.contains("b"));
+        assert!(merged.get("main").unwrap().children.contains("c"));
+        assert_eq!(merged.branches().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn merge_prefers_ours_on_conflict() {
+        let base = tree(&[("main", "a")]);
+        let mut ours = base.clone();
+        ours.get_mut("a").unwrap().remote = Some(RemoteMetadata::new(1));
+        let mut theirs = base.clone();
+        theirs.get_mut("a").unwrap().remote = Some(RemoteMetadata::new(2));
+
+        let (merged, conflicts) = StackTree::merge(&base, &ours, &theirs);
+        assert_eq!(conflicts, vec!["a".to_string()]);
+        assert_eq!(
+            merged.get("a").unwrap().remote.as_ref().unwrap().pr_number,
+            1
+        );
+    }
+
+    #[test]
+    fn merge_ignores_cached_pull_request_state() {
+        let cached = |state, fetched_at| CachedPullRequest {
+            title: "Add a".to_string(),
+            state,
+            review: ReviewStatus::None,
+            checks: CheckStatus::Pending,
+            failing_checks: Vec::new(),
+            mergeable: Mergeability::Unknown,
+            unresolved_threads: None,
+            head_sha: "abc".to_string(),
+            fetched_at,
+        };
+        let mut base = tree(&[("main", "a")]);
+        base.get_mut("a").unwrap().remote = Some(RemoteMetadata::new(1));
+        let mut ours = base.clone();
+        ours.get_mut("a").unwrap().remote.as_mut().unwrap().cache =
+            Some(cached(PullRequestState::Draft, 10));
+        let mut theirs = base.clone();
+        theirs.get_mut("a").unwrap().remote.as_mut().unwrap().cache =
+            Some(cached(PullRequestState::Open, 20));
+
+        let (merged, conflicts) = StackTree::merge(&base, &ours, &theirs);
+        assert!(conflicts.is_empty());
+        assert_eq!(
+            merged.get("a").unwrap().remote,
+            ours.get("a").unwrap().remote
+        );
+
+        let cache = cached(PullRequestState::Open, 1_000);
+        assert!(cache.is_fresh(1_300, 300));
+        assert!(!cache.is_fresh(1_301, 300));
+        assert_eq!(
+            cache.annotation(1, 1_000 + 2 * 60 * 60),
+            "#1 🟢 Open · No reviews · ⏳ Checks pending, 2h ago"
+        );
+    }
+
+    #[test]
+    fn merge_resurrects_deleted_parent_of_new_child() {
+        let base = tree(&[("main", "a")]);
+        let mut ours = base.clone();
+        ours.delete("a").unwrap();
+        let theirs = tree(&[("main", "a"), ("a", "b")]);
+
+        let (merged, conflicts) = StackTree::merge(&base, &ours, &theirs);
+        assert!(conflicts.is_empty());
+        assert_eq!(merged.get("b").unwrap().parent.as_deref(), Some("a"));
+        assert!(merged.get("a").unwrap().children.contains("b"));
+        assert_eq!(merged.branches().unwrap().len(), 3);
+    }
+}